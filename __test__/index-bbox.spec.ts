@@ -9,6 +9,9 @@ import { Resvg } from '../index'
 const svgPath1 = join(__dirname, '../example/bbox.svg')
 const svgPath2 = join(__dirname, '../example/bbox2.svg')
 
+const round = (bbox?: { x: number; y: number; width: number; height: number }) =>
+  bbox && [bbox.x, bbox.y, bbox.width, bbox.height].map((v) => Math.round(v))
+
 test('should handle cropByBBox with various padding values', async (t) => {
   const svg = await promises.readFile(svgPath2)
 
@@ -524,3 +527,117 @@ test('should cropByBBox with fitTo and padding', async (t) => {
   // Compare the two images
   t.is(jimp.diff(result, extened_result, 0.01).percent, 0) // 0 means similar, 1 means not similar
 })
+
+test('should get the bounding box of single elements', (t) => {
+  const svg = `<svg width="200" height="100" viewBox="0 0 100 50" xmlns="http://www.w3.org/2000/svg">
+  <g id="group" transform="translate(10 10)">
+    <rect id="box" width="20" height="10" fill="red" stroke="blue" stroke-width="4"/>
+    <rect id="hidden" x="40" width="10" height="10" fill="red" fill-opacity="0"/>
+  </g>
+  </svg>`
+  const resvg = new Resvg(svg, { font: { loadSystemFonts: false } })
+
+  t.deepEqual(round(resvg.getElementBBox('box')), [10, 10, 20, 10])
+  t.deepEqual(round(resvg.getElementBBox('box', { kind: 'stroke' })), [8, 8, 24, 14])
+  t.deepEqual(round(resvg.getElementBBox('hidden')), [50, 10, 10, 10])
+  t.is(resvg.getElementBBox('hidden', { kind: 'visual' }), undefined)
+  t.deepEqual(round(resvg.getElementBBox('group')), [10, 10, 50, 10])
+
+  const bboxes = resvg.elementBBoxes()
+  t.deepEqual(Object.keys(bboxes), ['box', 'group', 'hidden'])
+  t.deepEqual(round(bboxes.box.user), [10, 10, 20, 10])
+  t.deepEqual(round(bboxes.box.pixel), [20, 20, 40, 20])
+
+  const error = t.throws(() => resvg.getElementBBox('missing'), { instanceOf: Error }) as Error & { code: string }
+  t.is(error.code, 'ERR_ELEMENT_NOT_FOUND')
+})
+
+test('should find the elements painted at a point', (t) => {
+  const svg = `<svg width="200" height="100" viewBox="0 0 100 50" xmlns="http://www.w3.org/2000/svg">
+  <clipPath id="clip"><rect width="30" height="50"/></clipPath>
+  <g id="group" transform="translate(10 10)" clip-path="url(#clip)">
+    <rect id="box" width="40" height="10" fill="red" stroke="blue" stroke-width="4"/>
+    <rect id="hidden" x="5" width="10" height="10" fill="red" visibility="hidden"/>
+  </g>
+  <circle id="ring" cx="70" cy="30" r="10" fill="none" stroke="black" stroke-width="2"/>
+  </svg>`
+  const resvg = new Resvg(svg, { font: { loadSystemFonts: false } })
+
+  // Pixels are twice the user units.
+  t.deepEqual(resvg.elementsAtPoint(30, 30), ['group', 'box'])
+  // The stroke of the box.
+  t.deepEqual(resvg.elementsAtPoint(20, 16), ['group', 'box'])
+  // Outside of the clip path.
+  t.deepEqual(resvg.elementsAtPoint(90, 30), [])
+  // Only the stroke of the ring is painted.
+  t.deepEqual(resvg.elementsAtPoint(140, 60), [])
+  t.deepEqual(resvg.elementsAtPoint(140, 40), ['ring'])
+})
+
+test('should include filters and dashes in the ink bounding box', (t) => {
+  const svg = `<svg width="100" height="100" viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+  <filter id="shadow" filterUnits="userSpaceOnUse" x="0" y="0" width="80" height="70">
+    <feDropShadow dx="10" dy="10" stdDeviation="5"/>
+  </filter>
+  <rect x="10" y="10" width="50" height="40" fill="red" filter="url(#shadow)"/>
+  </svg>`
+  const resvg = new Resvg(svg, { font: { loadSystemFonts: false } })
+
+  t.deepEqual(round(resvg.innerBBox()), [10, 10, 50, 40])
+  t.deepEqual(round(resvg.innerBBox({ kind: 'ink' })), [0, 0, 80, 70])
+
+  const dashed = new Resvg(
+    `<svg width="100" height="100" viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+    <path id="line" d="M10 50 H90" stroke="black" stroke-width="2" stroke-dasharray="40 100"/>
+    </svg>`,
+    { font: { loadSystemFonts: false } },
+  )
+  t.deepEqual(round(dashed.getElementBBox('line', { kind: 'ink' })), [10, 49, 40, 2])
+  t.deepEqual(round(dashed.getElementBBox('line', { kind: 'stroke' })), [10, 49, 80, 2])
+})
+
+test('should reset and stack crops', (t) => {
+  const svg = `<svg width="100" height="100" viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+  <rect width="100" height="100" fill="red"/>
+  </svg>`
+  const resvg = new Resvg(svg, { font: { loadSystemFonts: false } })
+  t.is(resvg.getCrop(), undefined)
+
+  resvg.cropByBBox({ x: 20, y: 30, width: 40, height: 50 })
+  t.deepEqual(round(resvg.getCrop()), [20, 30, 40, 50])
+  t.is(resvg.width, 40)
+
+  resvg.cropByBBox({ x: 10, y: 10, width: 20, height: 20 }, 0, false, true)
+  t.deepEqual(round(resvg.getCrop()), [30, 40, 20, 20])
+
+  resvg.resetCrop()
+  t.is(resvg.getCrop(), undefined)
+  t.is(resvg.width, 100)
+  t.is(resvg.render().height, 100)
+})
+
+test('should crop with per side padding, an aspect ratio and normalized coordinates', (t) => {
+  const svg = `<svg width="100" height="100" viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+  <rect id="box" x="20" y="30" width="40" height="20" fill="red"/>
+  </svg>`
+  const font = { loadSystemFonts: false }
+
+  const padded = new Resvg(svg, { font })
+  padded.cropByBBox({ x: 20, y: 30, width: 40, height: 20 }, { padding: { left: 10, top: 5 } })
+  t.deepEqual(round(padded.getCrop()), [7, 23, 53, 27])
+  t.is(padded.width, 40)
+
+  const ratio = new Resvg(svg, { font })
+  ratio.cropByBBox({ x: 20, y: 30, width: 40, height: 20 }, { aspectRatio: 1 })
+  t.deepEqual(round(ratio.getCrop()), [20, 20, 40, 40])
+
+  const normalized = new Resvg(svg, { font })
+  normalized.cropByBBox({ x: 20, y: 30, width: 40, height: 20 }, { normalize: true })
+  t.deepEqual(round(normalized.getCrop()), [20, 30, 40, 20])
+  t.regex(normalized.toString(), /viewBox="0 0 40 20"/)
+  t.deepEqual(round(normalized.getElementBBox('box')), [0, 0, 40, 20])
+
+  normalized.resetCrop()
+  t.deepEqual(round(normalized.getElementBBox('box')), [20, 30, 40, 20])
+  t.is(normalized.getCrop(), undefined)
+})
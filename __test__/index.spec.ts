@@ -1,11 +1,22 @@
 import { promises as fs } from 'fs'
+import { tmpdir } from 'os'
 import { join } from 'path'
+import { gzipSync } from 'zlib'
 
 import test from 'ava'
 import jimp from 'jimp-compact'
 import fetch from 'node-fetch'
 
-import { Resvg, renderAsync } from '../index'
+import {
+  Resvg,
+  ResvgError,
+  measureText,
+  packSpriteSheet,
+  renderAsync,
+  renderFileAsync,
+  setLogLevel,
+  setLogger,
+} from '../index'
 
 import { jimpToRgbaPixels } from './helper'
 
@@ -406,6 +417,33 @@ test('should generate a 80x80 png and opaque', async (t) => {
   t.is(result.hasAlpha(), false)
 })
 
+test('should crop with percentages and user space coordinates', (t) => {
+  const svg = `<svg width="200" height="100" viewBox="0 0 100 50" xmlns="http://www.w3.org/2000/svg">
+    <rect fill="green" x="0" y="0" width="100" height="50"></rect>
+  </svg>`
+  const font = { loadSystemFonts: false }
+  const percent = new Resvg(svg, { font, crop: { left: '25%', top: 0, right: '75%', bottom: '50%' } }).render()
+  t.is(percent.width, 100)
+  t.is(percent.height, 50)
+
+  const user = new Resvg(svg, { font, crop: { left: 10.25, top: 5, right: 60, units: 'user' } }).render()
+  t.is(user.width, 120 - 21)
+  t.is(user.height, 90)
+
+  for (const crop of [
+    { left: 50, top: 0, right: 40 },
+    { left: 0, top: 0, right: 300 },
+    { left: -10, top: 0 },
+  ]) {
+    const error = t.throws(() => new Resvg(svg, { font, crop }).render(), { instanceOf: Error }) as ResvgError
+    t.is(error.code, 'ERR_INVALID_CROP')
+  }
+  const error = t.throws(() => new Resvg(svg, { font, crop: { left: '10px', top: 0 } as any }), {
+    instanceOf: Error,
+  }) as ResvgError
+  t.is(error.code, 'ERR_INVALID_OPTIONS')
+})
+
 // Generate a 100x100 transparent png starting from resvg 0.21.0
 // https://github.com/RazrFalcon/resvg/commit/5998e9b8411ff3f0171515371938ee1940be17c3
 test('should generate a 100x100 transparent png', async (t) => {
@@ -586,6 +624,759 @@ test('should render using font buffer provided by options', async (t) => {
   t.is(jimp.diff(expectedResult, actualPng, 0.01).percent, 0) // 0 means similar, 1 means not similar
 })
 
+test('should get text content with positions', (t) => {
+  const svg = `<svg width="480" height="150" viewBox="0 0 480 150" xmlns="http://www.w3.org/2000/svg">
+  <text id="title" x="10" y="100" font-size="60" font-family="Pacifico" fill="#ff0000">Hello</text>
+  </svg>`
+
+  const resvg = new Resvg(svg, {
+    font: {
+      fontFiles: ['./__test__/Pacifico-Regular.ttf'],
+      loadSystemFonts: false,
+    },
+  })
+  const chunks = resvg.textContent()
+
+  t.is(chunks.length, 1)
+  t.is(chunks[0].id, 'title')
+  t.is(chunks[0].text, 'Hello')
+  t.is(chunks[0].fontFamily, 'Pacifico')
+  t.is(chunks[0].fontSize, 60)
+  t.is(chunks[0].fill, '#ff0000')
+
+  const bbox = chunks[0].bbox
+  t.truthy(bbox)
+  if (bbox) {
+    t.true(bbox.x >= 10)
+    t.true(bbox.width > 0)
+    t.true(bbox.y < 100)
+  }
+})
+
+test('should measure text without rendering', (t) => {
+  const font = {
+    fontFiles: ['./__test__/Pacifico-Regular.ttf'],
+    loadSystemFonts: false,
+  }
+  const metrics = measureText('Hello', { font, fontFamily: 'Pacifico', fontSize: 40 })
+
+  t.true(metrics.width > 0)
+  t.true(metrics.ascent > 0)
+  t.true(metrics.descent > 0)
+  t.is(metrics.glyphs.length, 5)
+  t.is(metrics.glyphs[0].x, 0)
+
+  const spaced = measureText('Hello', { font, fontFamily: 'Pacifico', fontSize: 40, letterSpacing: 2 })
+  // Like `render()`, usvg adds no spacing after the last character.
+  t.is(Math.round(spaced.width - metrics.width), 8)
+})
+
+test('should resolve images with imageResolver, including nested in pattern', async (t) => {
+  const png = await fs.readFile(join(__dirname, './options_font_buffer_expected_result.png'))
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <defs>
+    <pattern id="p" width="100" height="100" patternUnits="userSpaceOnUse">
+      <image href="https://example.com/nested.png" width="100" height="100"/>
+    </pattern>
+  </defs>
+  <rect width="100" height="100" fill="url(#p)"/>
+  </svg>`
+
+  const hrefs: string[] = []
+  const resvg = new Resvg(svg, {
+    font: {
+      loadSystemFonts: false,
+    },
+    imageResolver: (href) => {
+      hrefs.push(href)
+      return png
+    },
+  })
+
+  t.deepEqual(hrefs, ['https://example.com/nested.png'])
+  t.deepEqual(resvg.imagesToResolve(), [])
+  const pixels = resvg.render().pixels
+  t.true(pixels.some((v, i) => i % 4 === 3 && v > 0))
+})
+
+test('should throw when imageResolver returns an unsupported buffer', (t) => {
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="https://example.com/a.png" width="100" height="100"/>
+  </svg>`
+
+  const error = t.throws(() => {
+    new Resvg(svg, {
+      font: {
+        loadSystemFonts: false,
+      },
+      imageResolver: () => Buffer.from('not an image'),
+    })
+  }) as ResvgError
+  t.is(error.code, 'ERR_UNSUPPORTED_IMAGE')
+  t.true(error.message.includes('https://example.com/a.png'))
+})
+
+test('should resolve images asynchronously with renderAsync imageResolver', async (t) => {
+  const png = await fs.readFile(join(__dirname, './options_font_buffer_expected_result.png'))
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="https://example.com/a.png" width="100" height="100"/>
+  <image href="https://example.com/missing.png" width="100" height="100"/>
+  </svg>`
+
+  const result = await renderAsync(svg, {
+    font: {
+      loadSystemFonts: false,
+    },
+    imageResolver: async (href) => (href.endsWith('a.png') ? png : null),
+  })
+
+  t.is(result.width, 100)
+  t.true(result.pixels.some((v, i) => i % 4 === 3 && v > 0))
+})
+
+test('should resolve relative image hrefs inside resourcesDir only', (t) => {
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="options_font_buffer_expected_result.png" width="100" height="100"/>
+  <image href="../example/bbox-out.png" width="100" height="100"/>
+  </svg>`
+  const opts = {
+    font: {
+      loadSystemFonts: false,
+    },
+    resourcesDir: __dirname,
+  }
+
+  const resvg = new Resvg(svg, opts)
+  t.truthy(resvg.innerBBox())
+  t.is(resvg.toString().match(/<image/g)?.length, 1)
+
+  const unsandboxed = new Resvg(svg, { ...opts, resourcesSandbox: false })
+  t.is(unsandboxed.toString().match(/<image/g)?.length, 2)
+})
+
+test('should percent-decode file:// image hrefs', (t) => {
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="file://options%5Ffont%5Fbuffer%5Fexpected%5Fresult.png" width="100" height="100"/>
+  </svg>`
+
+  const resvg = new Resvg(svg, { font: { loadSystemFonts: false }, resourcesDir: __dirname })
+  t.is(resvg.toString().match(/<image/g)?.length, 1)
+})
+
+test('should collect warnings per instance', (t) => {
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="../example/bbox-out.png" width="100" height="100"/>
+  </svg>`
+  const font = {
+    loadSystemFonts: false,
+  }
+
+  const resvg = new Resvg(svg, { font, resourcesDir: __dirname })
+  resvg.render()
+  const warnings = resvg.warnings()
+  t.is(warnings.length, 1)
+  t.is(warnings[0].level, 'warn')
+  t.is(warnings[0].target, 'resvg_js::options')
+  t.is(warnings[0].message, "'../example/bbox-out.png' is outside of the resources directory. Skipped.")
+
+  const clean = new Resvg(`<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg"><rect width="10" height="10"/></svg>`, {
+    font,
+  })
+  clean.render()
+  t.deepEqual(clean.warnings(), [])
+})
+
+test('should forward log records to setLogger', async (t) => {
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="../example/bbox-out.png" width="100" height="100"/>
+  </svg>`
+  const records: string[][] = []
+  setLogger((level, target, message) => {
+    records.push([level, target, message])
+  })
+  t.teardown(() => setLogger(null))
+
+  new Resvg(svg, { font: { loadSystemFonts: false }, resourcesDir: __dirname, logLevel: 'warn' })
+  await new Promise((resolve) => setTimeout(resolve, 100))
+  t.true(
+    records.some(
+      ([level, target, message]) =>
+        level === 'warn' && target === 'resvg_js::options' && message.includes('outside of the resources directory'),
+    ),
+  )
+
+  // @ts-expect-error
+  const error = t.throws(() => setLogLevel('verbose'), { instanceOf: Error }) as ResvgError
+  t.is(error.code, 'ERR_INVALID_LOG_LEVEL')
+})
+
+test('should resolve SVG, BMP and reject unsupported images in resolveImage', async (t) => {
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="https://example.com/a.svg" width="50" height="50"/>
+  <image href="https://example.com/b.bmp" x="50" y="50" width="50" height="50"/>
+  <image href="https://example.com/c.avif" width="100" height="100"/>
+  </svg>`
+  const resvg = new Resvg(svg, {
+    font: {
+      loadSystemFonts: false,
+    },
+  })
+
+  const nested = Buffer.from(
+    '<svg width="10" height="10" xmlns="http://www.w3.org/2000/svg"><rect width="10" height="10" fill="#00f"/></svg>',
+  )
+  resvg.resolveImage('https://example.com/a.svg', nested)
+
+  // A 1x1 red 24-bit BMP
+  const bmp = Buffer.alloc(58)
+  bmp.write('BM', 0)
+  bmp.writeUInt32LE(58, 2)
+  bmp.writeUInt32LE(54, 10)
+  bmp.writeUInt32LE(40, 14)
+  bmp.writeInt32LE(1, 18)
+  bmp.writeInt32LE(1, 22)
+  bmp.writeUInt16LE(1, 26)
+  bmp.writeUInt16LE(24, 28)
+  bmp.writeUInt32LE(4, 34)
+  bmp.set([0x00, 0x00, 0xff, 0x00], 54)
+  resvg.resolveImage('https://example.com/b.bmp', bmp)
+
+  const avif = await fs.readFile(join(__dirname, '../example/out-resvg-js@2x.avif'))
+  const error = t.throws(() => resvg.resolveImage('https://example.com/c.avif', avif), {
+    instanceOf: Error,
+  }) as ResvgError
+  t.is(error.code, 'ERR_UNDECODABLE_IMAGE')
+  t.is(error.message, 'AVIF is detected but not supported by this build')
+  const unknown = t.throws(() => resvg.resolveImage('https://example.com/c.avif', Buffer.from('not an image')))
+  t.is((unknown as ResvgError).code, 'ERR_UNSUPPORTED_IMAGE')
+  const resolved = t.throws(() => new Resvg(svg, { font: { loadSystemFonts: false }, imageResolver: () => avif }))
+  t.is((resolved as ResvgError).code, 'ERR_UNDECODABLE_IMAGE')
+
+  const pixels = resvg.render().pixels
+  t.deepEqual(Array.from(pixels.subarray(0, 4)), [0, 0, 255, 255])
+  const last = pixels.length - 4
+  t.deepEqual(Array.from(pixels.subarray(last, last + 4)), [255, 0, 0, 255])
+})
+
+test('should render the text of resolved SVG images with the parent fonts', (t) => {
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="https://example.com/text.svg" width="100" height="100"/>
+  </svg>`
+  const nested = Buffer.from(
+    '<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg"><text x="10" y="60" font-family="Pacifico" font-size="40">Hi</text></svg>',
+  )
+  const resvg = new Resvg(svg, {
+    font: {
+      fontFiles: ['./__test__/Pacifico-Regular.ttf'],
+      loadSystemFonts: false,
+    },
+    imageResolver: () => nested,
+  })
+
+  const pixels = resvg.render().pixels
+  t.true(pixels.some((v, i) => i % 4 === 3 && v > 0))
+})
+
+test('should not load the images of resolved SVG images', async (t) => {
+  const png = await fs.readFile(join(__dirname, './options_font_buffer_expected_result.png'))
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="https://example.com/nested.svg" width="100" height="100"/>
+  </svg>`
+  // The 480x150 data URI is over the maxImagePixels limit, it must not be decoded either.
+  const nested = Buffer.from(
+    `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg"><image href="data:image/png;base64,${png.toString('base64')}" width="100" height="100"/></svg>`,
+  )
+  const resvg = new Resvg(svg, {
+    font: {
+      loadSystemFonts: false,
+    },
+    limits: { maxImagePixels: 1000 },
+    imageResolver: () => nested,
+  })
+
+  const pixels = resvg.render().pixels
+  t.true(pixels.every((v, i) => i % 4 !== 3 || v === 0))
+})
+
+test('should enforce image limits before decoding', async (t) => {
+  const png = await fs.readFile(join(__dirname, './options_font_buffer_expected_result.png'))
+  const href = `data:image/png;base64,${png.toString('base64')}`
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="${href}" width="100" height="100"/>
+  <image href="${href}" width="50" height="50"/>
+  </svg>`
+  const font = {
+    loadSystemFonts: false,
+  }
+
+  const pixelsError = t.throws(() => new Resvg(svg, { font, limits: { maxImagePixels: 1000 } }), {
+    instanceOf: Error,
+  })
+  t.is(pixelsError.message, "Image 'data:image/png' exceeds the maxImagePixels limit (480x150 > 1000 pixels)")
+
+  const bytesError = t.throws(() => new Resvg(svg, { font, limits: { maxImageBytes: 100 } }), { instanceOf: Error })
+  t.true(bytesError.message.includes('maxImageBytes'))
+
+  const countError = t.throws(() => new Resvg(svg, { font, limits: { maxImages: 1 } }), { instanceOf: Error })
+  t.is(countError.message, 'The number of images exceeds the maxImages limit (1)')
+
+  const skipped = new Resvg(svg, { font, limits: { maxImagePixels: 1000, skipOversizedImages: true } })
+  t.is(skipped.innerBBox(), undefined)
+})
+
+test('should enforce output size, node count and use depth limits', (t) => {
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+  <defs>
+    <rect id="a" width="10" height="10"/>
+    <g id="b"><use xlink:href="#a"/><use xlink:href="#a"/></g>
+    <g id="c"><use xlink:href="#b"/><use xlink:href="#b"/></g>
+  </defs>
+  <use xlink:href="#c"/>
+  </svg>`
+  const font = {
+    loadSystemFonts: false,
+  }
+
+  const zoomError = t.throws(
+    () => new Resvg(svg, { font, fitTo: { mode: 'zoom', value: 100 }, limits: { maxPixels: 1000000 } }),
+    { instanceOf: Error },
+  )
+  t.is(zoomError.message, 'Output size 10000x10000 exceeds the maxPixels limit (1000000 pixels)')
+
+  const widthError = t.throws(() => new Resvg(svg, { font, limits: { maxWidth: 50 } }), { instanceOf: Error })
+  t.is(widthError.message, 'Output width 100 exceeds the maxWidth limit (50)')
+
+  const depthError = t.throws(() => new Resvg(svg, { font, limits: { maxUseDepth: 2 } }), { instanceOf: Error })
+  t.is(depthError.message, 'The nesting depth of <use> elements exceeds the maxUseDepth limit (2)')
+
+  const nodesError = t.throws(() => new Resvg(svg, { font, limits: { maxNodes: 20 } }), { instanceOf: Error })
+  t.is(nodesError.message, 'The number of elements exceeds the maxNodes limit (20)')
+
+  const resvg = new Resvg(svg, { font, limits: { maxUseDepth: 3, maxNodes: 100, renderTimeout: 10000 } })
+  t.is(resvg.render().width, 100)
+})
+
+test('should stop a long <use> chain at maxUseDepth', (t) => {
+  const links = Array.from({ length: 50000 }, (_, i) => `<use id="u${i + 1}" href="#u${i}"/>`).join('')
+  const svg = `<svg width="10" height="10" xmlns="http://www.w3.org/2000/svg">
+  <defs><rect id="u0" width="10" height="10"/>${links}</defs>
+  <use href="#u50000"/>
+  </svg>`
+  const error = t.throws(() => new Resvg(svg, { font: { loadSystemFonts: false }, limits: { maxUseDepth: 100 } }))
+  t.is((error as ResvgError).code, 'ERR_USE_DEPTH_LIMIT')
+})
+
+test('should render the same with renderTimeout, element by element', (t) => {
+  const svg = `<svg width="100" height="100" viewBox="0 0 50 50" xmlns="http://www.w3.org/2000/svg">
+  <g transform="translate(5 5)">
+    <g transform="scale(2)"><rect width="10" height="10" fill="#f00"/></g>
+    <g opacity="0.5"><rect x="10" y="10" width="20" height="20" fill="#00f"/><rect x="20" y="20" width="20" height="20" fill="#0f0"/></g>
+  </g>
+  </svg>`
+  const font = {
+    loadSystemFonts: false,
+  }
+
+  const expected = new Resvg(svg, { font }).render().pixels
+  const resvg = new Resvg(svg, { font, limits: { renderTimeout: 10000 } })
+  t.deepEqual(resvg.render().pixels, expected)
+
+  // Groups that need a layer are rendered as a whole, with their clip path, mask, filter or blend mode.
+  const layers = `<svg width="120" height="100" viewBox="0 0 80 40" preserveAspectRatio="xMidYMax meet" xmlns="http://www.w3.org/2000/svg">
+  <defs>
+    <clipPath id="clip"><circle cx="10" cy="10" r="8"/></clipPath>
+    <mask id="mask"><rect x="20" width="10" height="20" fill="#fff"/></mask>
+    <filter id="blur"><feGaussianBlur stdDeviation="2"/></filter>
+  </defs>
+  <rect width="80" height="40" fill="#ff0"/>
+  <g transform="translate(2 2)">
+    <g clip-path="url(#clip)"><rect width="20" height="20" fill="#f00"/></g>
+    <g mask="url(#mask)"><rect x="15" width="20" height="20" fill="#00f"/></g>
+    <g filter="url(#blur)"><rect x="40" y="5" width="10" height="10" fill="#0f0"/></g>
+    <g style="mix-blend-mode:multiply"><rect x="55" y="5" width="20" height="20" fill="#0ff"/></g>
+  </g>
+  </svg>`
+  t.deepEqual(
+    new Resvg(layers, { font, limits: { renderTimeout: 10000 } }).render().pixels,
+    new Resvg(layers, { font }).render().pixels,
+  )
+
+  const bbox = resvg.getBBox()!
+  resvg.cropByBBox(bbox, { normalize: true })
+  const cropped = new Resvg(svg, { font })
+  cropped.cropByBBox(bbox, { normalize: true })
+  t.deepEqual(resvg.render().pixels, cropped.render().pixels)
+})
+
+test('should throw on invalid options unless lenient', (t) => {
+  const svg = `<svg width="100" height="50" xmlns="http://www.w3.org/2000/svg"><rect width="100" height="50"/></svg>`
+  const options = {
+    font: { loadSystemFonts: false },
+    fitTo: { mode: 'widht', value: 200 },
+  }
+
+  // @ts-expect-error
+  const error = t.throws(() => new Resvg(svg, options), { instanceOf: Error })
+  t.true(error.message.startsWith('Invalid options, unknown variant `widht`'))
+
+  // @ts-expect-error
+  const resvg = new Resvg(svg, { ...options, lenient: true })
+  t.is(resvg.render().width, 100)
+})
+
+test('should set a code on thrown errors', async (t) => {
+  const font = {
+    loadSystemFonts: false,
+  }
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg"><rect width="10" height="10"/></svg>`
+
+  const zeroSized = t.throws(() => new Resvg(svg, { font, fitTo: { mode: 'width', value: 0 } }).render(), {
+    instanceOf: Error,
+  }) as ResvgError
+  t.is(zeroSized.code, 'ERR_ZERO_SIZED')
+
+  const invalid = `<svg xmlns="http://www.w3.org/2000/svg">
+  <g>
+  </svg>`
+  const parseError = t.throws(() => new Resvg(invalid, { font }), { instanceOf: Error }) as ResvgError
+  t.is(parseError.code, 'ERR_USVG_PARSE')
+  t.is(parseError.line, 3)
+  t.is(typeof parseError.column, 'number')
+
+  const asyncError = (await t.throwsAsync(renderAsync(invalid, { font }), { instanceOf: Error })) as ResvgError
+  t.is(asyncError.code, 'ERR_USVG_PARSE')
+})
+
+test('should render SVGZ input within the decompressed size limit', (t) => {
+  const svg = `<svg width="100" height="50" xmlns="http://www.w3.org/2000/svg"><rect width="100" height="50" fill="red"/></svg>`
+  const svgz = gzipSync(svg)
+  const font = {
+    loadSystemFonts: false,
+  }
+
+  const resvg = new Resvg(svgz, { font })
+  t.is(resvg.width, 100)
+  t.is(resvg.height, 50)
+
+  const error = t.throws(() => new Resvg(svgz, { font, limits: { maxDecompressedBytes: 64 } }), {
+    instanceOf: Error,
+  }) as ResvgError
+  t.is(error.code, 'ERR_DECOMPRESSED_SIZE_LIMIT')
+  t.is(error.message, 'The decompressed SVGZ exceeds the maxDecompressedBytes limit (64 bytes)')
+})
+
+test('should load local SVG images starting with a comment, within maxImageBytes', async (t) => {
+  const dir = await fs.mkdtemp(join(tmpdir(), 'resvg-js-'))
+  t.teardown(() => fs.rm(dir, { recursive: true, force: true }))
+  const image = `\uFEFF<!-- icon -->
+<svg width="10" height="10" xmlns="http://www.w3.org/2000/svg"><rect width="10" height="10" fill="#00f"/></svg>`
+  await fs.writeFile(join(dir, 'image.svg'), image)
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="image.svg" width="100" height="100"/>
+  </svg>`
+  const opts = {
+    font: {
+      loadSystemFonts: false,
+    },
+    resourcesDir: dir,
+  }
+
+  const pixels = new Resvg(svg, opts).render().pixels
+  t.deepEqual(Array.from(pixels.subarray(0, 4)), [0, 0, 255, 255])
+
+  const error = t.throws(() => new Resvg(svg, { ...opts, limits: { maxImageBytes: 64 } }), {
+    instanceOf: Error,
+  }) as ResvgError
+  t.is(error.code, 'ERR_IMAGE_BYTES_LIMIT')
+})
+
+test('should load SVG and SVGZ files with images relative to the file', async (t) => {
+  const dir = await fs.mkdtemp(join(tmpdir(), 'resvg-js-'))
+  t.teardown(() => fs.rm(dir, { recursive: true, force: true }))
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <image href="image.png" width="100" height="100"/>
+  </svg>`
+  await fs.copyFile(join(__dirname, 'options_font_buffer_expected_result.png'), join(dir, 'image.png'))
+  await fs.writeFile(join(dir, 'image.svg'), svg)
+  await fs.writeFile(join(dir, 'image.svgz'), gzipSync(svg))
+  const font = {
+    loadSystemFonts: false,
+  }
+
+  const resvg = Resvg.fromFile(join(dir, 'image.svg'), { font })
+  t.true(resvg instanceof Resvg)
+  t.is(resvg.width, 100)
+  t.is(resvg.toString().match(/<image/g)?.length, 1)
+  t.is(Resvg.fromFile(join(dir, 'image.svgz'), { font }).toString().match(/<image/g)?.length, 1)
+
+  const image = await renderFileAsync(join(dir, 'image.svgz'), { font })
+  t.is(image.width, 100)
+  t.deepEqual(image.pixels, resvg.render().pixels)
+
+  const error = t.throws(() => Resvg.fromFile(join(dir, 'missing.svg'), { font }), {
+    instanceOf: Error,
+  }) as ResvgError
+  t.is(error.code, 'ERR_READ_FILE')
+  const asyncError = (await t.throwsAsync(renderFileAsync(join(dir, 'missing.svg'), { font }))) as ResvgError
+  t.is(asyncError.code, 'ERR_READ_FILE')
+})
+
+test('should render a single element cropped to its bounding box', (t) => {
+  const svg = `<svg width="200" height="100" xmlns="http://www.w3.org/2000/svg">
+  <g id="icons" transform="translate(100 0)">
+    <rect id="red" x="10" y="20" width="30" height="40" fill="red"/>
+    <rect id="blue" x="50" y="20" width="30" height="40" fill="blue"/>
+  </g>
+  </svg>`
+  const resvg = new Resvg(svg, { font: { loadSystemFonts: false } })
+
+  const red = resvg.renderElement('red')
+  t.is(red.width, 30)
+  t.is(red.height, 40)
+  t.deepEqual([...red.pixels.subarray(0, 4)], [255, 0, 0, 255])
+  t.deepEqual([...red.pixels.subarray(-4)], [255, 0, 0, 255])
+
+  const padded = resvg.renderElement('blue', { padding: 5, fitTo: { mode: 'zoom', value: 2 } })
+  t.is(padded.width, 80)
+  t.is(padded.height, 100)
+  t.deepEqual([...padded.pixels.subarray(0, 4)], [0, 0, 0, 0])
+  const center = (50 * padded.width + 40) * 4
+  t.deepEqual([...padded.pixels.subarray(center, center + 4)], [0, 0, 255, 255])
+
+  const group = resvg.renderElement('icons')
+  t.is(group.width, 70)
+  t.is(group.height, 40)
+
+  const error = t.throws(() => resvg.renderElement('missing'), { instanceOf: Error }) as ResvgError
+  t.is(error.code, 'ERR_ELEMENT_NOT_FOUND')
+  t.is(error.message, "Element 'missing' not found")
+})
+
+test('should render every symbol and top-level group of a sprite', (t) => {
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <symbol id="square" viewBox="0 0 24 24"><rect x="2" y="2" width="20" height="20" fill="red"/></symbol>
+  <symbol id="dot"><circle cx="50" cy="50" r="5" fill="blue"/></symbol>
+  <symbol id="empty"/>
+  <g id="bar"><rect x="10" y="80" width="40" height="10" fill="green"/></g>
+  <rect width="10" height="10"/>
+  </svg>`
+  const resvg = new Resvg(svg, { font: { loadSystemFonts: false } })
+  t.is(resvg.toString().match(/<rect/g)?.length, 2)
+
+  const symbols = resvg.renderSymbols()
+  t.deepEqual(
+    symbols.map(({ id, image }) => [id, image.width, image.height]),
+    [
+      ['square', 24, 24],
+      ['dot', 10, 10],
+      ['bar', 40, 10],
+    ],
+  )
+
+  const png = resvg.renderSymbols({ format: 'png', fitTo: { mode: 'zoom', value: 2 } })
+  t.is(png[0].id, 'square')
+  t.true(Buffer.isBuffer(png[0].image))
+  t.is(png[0].image.readUInt32BE(16), 48)
+
+  // `renderElement()` crops symbols to their bounding box, like other elements.
+  const square = resvg.renderElement('square', { padding: 1 })
+  t.is(square.width, 22)
+  t.is(square.height, 22)
+  t.deepEqual([...square.pixels.subarray(0, 4)], [0, 0, 0, 0])
+  const center = (11 * square.width + 11) * 4
+  t.deepEqual([...square.pixels.subarray(center, center + 4)], [255, 0, 0, 255])
+
+  // The symbols are parsed once, rendering them does not change them.
+  t.deepEqual([...resvg.renderElement('square', { padding: 1 }).pixels], [...square.pixels])
+  t.deepEqual(
+    resvg.renderSymbols().map(({ id, image }) => [id, image.width, image.height]),
+    symbols.map(({ id, image }) => [id, image.width, image.height]),
+  )
+})
+
+test('should not count the symbols of renderSymbols against the limits of the document', async (t) => {
+  const png = await fs.readFile(join(__dirname, './options_font_buffer_expected_result.png'))
+  const svg = `<svg width="100" height="100" xmlns="http://www.w3.org/2000/svg">
+  <symbol id="photo" viewBox="0 0 100 100"><image href="data:image/png;base64,${png.toString('base64')}" width="100" height="100"/></symbol>
+  <use href="#photo" width="100" height="100"/>
+  </svg>`
+  const resvg = new Resvg(svg, { font: { loadSystemFonts: false }, limits: { maxImages: 1, maxNodes: 6 } })
+
+  t.is(resvg.toString().match(/<image/g)?.length, 1)
+  t.deepEqual(
+    resvg.renderSymbols().map(({ id }) => id),
+    ['photo'],
+  )
+})
+
+test('should pack several SVGs into a sprite sheet', (t) => {
+  const rect = (width: number, height: number, fill: string) =>
+    `<svg width="${width}" height="${height}" xmlns="http://www.w3.org/2000/svg"><rect width="${width}" height="${height}" fill="${fill}"/></svg>`
+  const { image, frames } = packSpriteSheet([rect(10, 10, 'red'), rect(20, 30, 'blue'), rect(30, 20, 'green')], {
+    font: { loadSystemFonts: false },
+    padding: 2,
+    maxWidth: 54,
+  })
+  t.deepEqual(frames, [
+    { x: 0, y: 32, width: 10, height: 10 },
+    { x: 0, y: 0, width: 20, height: 30 },
+    { x: 22, y: 0, width: 30, height: 20 },
+  ])
+  t.is(image.width, 52)
+  t.is(image.height, 42)
+  t.is(JSON.stringify(frames[0]), '{"x":0,"y":32,"width":10,"height":10}')
+
+  const pixel = (x: number, y: number) => [...image.pixels.subarray((y * image.width + x) * 4, (y * image.width + x) * 4 + 4)]
+  t.deepEqual(pixel(5, 37), [255, 0, 0, 255])
+  t.deepEqual(pixel(10, 15), [0, 0, 255, 255])
+  t.deepEqual(pixel(40, 10), [0, 128, 0, 255])
+  t.deepEqual(pixel(21, 10), [0, 0, 0, 0])
+})
+
+test('should trim the transparent pixels', (t) => {
+  const svg = `<svg width="100" height="80" xmlns="http://www.w3.org/2000/svg">
+  <rect x="20" y="10" width="30" height="40" fill="red"/>
+  <rect x="70" y="60" width="10" height="10" fill="blue" fill-opacity="0.1"/>
+  </svg>`
+  const font = { loadSystemFonts: false }
+  const image = new Resvg(svg, { font }).render()
+  const bounds = (bbox?: { x: number; y: number; width: number; height: number }) =>
+    bbox && [bbox.x, bbox.y, bbox.width, bbox.height]
+  t.deepEqual(bounds(image.opaqueBounds()), [20, 10, 60, 60])
+  t.deepEqual(bounds(image.opaqueBounds(128)), [20, 10, 30, 40])
+
+  const trimmed = new Resvg(svg, { font, trim: { threshold: 128, padding: 5 } }).render()
+  t.is(trimmed.width, 40)
+  t.is(trimmed.height, 50)
+  t.deepEqual(bounds(trimmed.opaqueBounds(128)), [5, 5, 30, 40])
+
+  const empty = new Resvg('<svg width="10" height="10" xmlns="http://www.w3.org/2000/svg"></svg>', {
+    font,
+    trim: {},
+  }).render()
+  t.is(empty.opaqueBounds(), undefined)
+  t.is(empty.width, 10)
+})
+
+test('should render tiles and stream a PNG', async (t) => {
+  const svg = `<svg width="100" height="60" xmlns="http://www.w3.org/2000/svg">
+  <rect width="100" height="60" fill="blue"/>
+  <circle cx="50" cy="30" r="25" fill="red" fill-opacity="0.5"/>
+  </svg>`
+  const resvg = new Resvg(svg, { font: { loadSystemFonts: false }, fitTo: { mode: 'zoom', value: 2 } })
+  const full = resvg.render()
+
+  const tiles: Array<[number, number, number, number]> = []
+  resvg.renderTiles({ tileSize: 128 }, ({ x, y, width, height, image }) => {
+    tiles.push([x, y, width, height])
+    for (let row = 0; row < height; row++) {
+      const start = ((y + row) * full.width + x) * 4
+      t.deepEqual(
+        image.pixels.subarray(row * width * 4, (row + 1) * width * 4),
+        full.pixels.subarray(start, start + width * 4),
+      )
+    }
+  })
+  t.deepEqual(tiles, [
+    [0, 0, 128, 120],
+    [128, 0, 72, 120],
+  ])
+
+  const png: Array<Buffer> = []
+  resvg.renderTiles({ tileSize: 100, format: 'png' }, ({ image }) => png.push(image))
+  t.is(png.length, 4)
+  t.is(png[3].readUInt32BE(16), 100)
+  t.is(png[3].readUInt32BE(20), 20)
+
+  const chunks: Array<Buffer> = []
+  resvg.renderPngStream({ stripHeight: 7 }, (chunk) => chunks.push(chunk))
+  const streamed = Buffer.concat(chunks)
+  t.deepEqual(
+    await jimpToRgbaPixels(streamed, 200, 120),
+    await jimpToRgbaPixels(full.asPng(), 200, 120),
+  )
+
+  const error = t.throws(() =>
+    resvg.renderTiles({}, () => {
+      throw new Error('stop')
+    }),
+  )
+  t.is(error?.message, 'stop')
+  t.is((error as ResvgError | undefined)?.code, undefined)
+})
+
+test('should render a region of user space', (t) => {
+  const svg = `<svg width="200" height="200" viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+  <rect x="10" y="10" width="10" height="10" fill="red"/>
+  <rect x="20" y="10" width="10" height="10" fill="blue"/>
+  </svg>`
+  const resvg = new Resvg(svg, { font: { loadSystemFonts: false } })
+  const pixel = (image: { width: number; pixels: Buffer }, x: number, y: number) => [
+    ...image.pixels.subarray((y * image.width + x) * 4, (y * image.width + x) * 4 + 4),
+  ]
+
+  const region = resvg.renderRegion({ x: 10, y: 10, width: 20, height: 10 }, { scale: 4 })
+  t.is(region.width, 80)
+  t.is(region.height, 40)
+  t.deepEqual(pixel(region, 1, 1), [255, 0, 0, 255])
+  t.deepEqual(pixel(region, 78, 38), [0, 0, 255, 255])
+
+  const scaled = resvg.renderRegion({ x: 15, y: 10, width: 10, height: 5 }, { outputWidth: 30 })
+  t.is(scaled.width, 30)
+  t.is(scaled.height, 15)
+  t.deepEqual(pixel(scaled, 14, 7), [255, 0, 0, 255])
+  t.deepEqual(pixel(scaled, 16, 7), [0, 0, 255, 255])
+
+  // The viewbox is left unchanged.
+  t.is(resvg.width, 200)
+  t.is(resvg.render().width, 200)
+
+  const error = t.throws(() => resvg.renderRegion({ x: 0, y: 0, width: 0, height: 10 })) as ResvgError
+  t.is(error.code, 'ERR_INVALID_REGION')
+  const both = t.throws(() => resvg.renderRegion({ x: 0, y: 0, width: 10, height: 10 }, { scale: 1, outputWidth: 5 }))
+  t.is((both as ResvgError).code, 'ERR_INVALID_REGION')
+})
+
+test('should export a deep zoom tile pyramid', (t) => {
+  const svg = `<svg width="300" height="200" xmlns="http://www.w3.org/2000/svg">
+  <rect width="300" height="200" fill="red"/>
+  </svg>`
+  const resvg = new Resvg(svg, { font: { loadSystemFonts: false } })
+  const pyramid = resvg.exportTilePyramid({ tileSize: 256, minLevel: 8, maxLevel: 20 })
+  t.is(pyramid.width, 300)
+  t.is(pyramid.height, 200)
+  t.is(pyramid.minLevel, 8)
+  t.is(pyramid.maxLevel, 9)
+  t.true(pyramid.dzi.includes('Format="png" Overlap="1" TileSize="256"'))
+  t.true(pyramid.dzi.includes('<Size Width="300" Height="200"/>'))
+  t.deepEqual(
+    pyramid.tiles.map(({ level, column, row, x, y, width, height }) => [level, column, row, x, y, width, height]),
+    [
+      [8, 0, 0, 0, 0, 150, 100],
+      [9, 0, 0, 0, 0, 257, 200],
+      [9, 1, 0, 255, 0, 45, 200],
+    ],
+  )
+  t.is(pyramid.tiles[2].image.readUInt32BE(16), 45)
+
+  const { tiles } = resvg.exportTilePyramid({ format: 'rgba', minLevel: 0, maxLevel: 1 })
+  t.deepEqual(
+    tiles.map(({ image }) => [image.width, image.height]),
+    [
+      [1, 1],
+      [2, 1],
+    ],
+  )
+  // The level is rounded up, the image only covers a part of the pixel.
+  t.true(tiles[0].image.pixels[3] > 0)
+  // Raw RGBA tiles are not a DZI tile format.
+  t.is(resvg.exportTilePyramid({ format: 'rgba', minLevel: 0, maxLevel: 0 }).dzi, undefined)
+
+  const error = t.throws(() => resvg.exportTilePyramid({ minLevel: 20 })) as ResvgError
+  t.is(error.code, 'ERR_INVALID_LEVELS')
+  t.is(error.message, 'Invalid levels, minLevel 20 is greater than maxLevel 9')
+})
+
 test('should throw because invalid SVG attribute (width attribute is 0)', (t) => {
   const error = t.throws(
     () => {
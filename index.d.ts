@@ -32,14 +32,91 @@ export type ResvgRenderOptions = {
     | { mode: 'height'; value: number }
     | { mode: 'zoom'; value: number }
   background?: string // Support CSS3 color, e.g. rgba(255, 255, 255, .8)
+  // Numbers are output pixels, or user space with `units: 'user'`. Percentages like '25%' are relative to the output size.
+  // Throws `ERR_INVALID_CROP` when the rectangle is empty, inverted or outside of the image.
   crop?: {
-    left: number
-    top: number
-    right?: number
-    bottom?: number
+    left: number | `${number}%`
+    top: number | `${number}%`
+    right?: number | `${number}%`
+    bottom?: number | `${number}%`
+    units?: 'pixels' | 'user' // Default: 'pixels'
   }
-  logLevel?: 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace'
+  // Trim the transparent pixels around the content after `crop`, e.g. `trim: {}`.
+  // Fully transparent images are kept as they are, and nothing is trimmed with an opaque `background`.
+  trim?: {
+    threshold?: number // Default: 0, pixels with an alpha at or below it (0-255) are trimmed.
+    padding?: number // Default: 0, the transparent pixels kept around the content.
+  }
+  logLevel?: LogLevel // Default: the level set by `setLogLevel()`, 'error' unless changed. Only applies to this instance.
+  lenient?: boolean // Default: false, fall back to the default options instead of throwing when the options are invalid.
+  limits?: {
+    maxImagePixels?: number // The maximum width * height of a single image, read from its header before decoding.
+    maxImageBytes?: number // The maximum encoded size of a single image in bytes.
+    maxImages?: number // The maximum number of images in the SVG.
+    skipOversizedImages?: boolean // Default: false, skip images over the limits instead of throwing.
+    maxWidth?: number // The maximum width of the rendered image, after `fitTo` is applied.
+    maxHeight?: number // The maximum height of the rendered image, after `fitTo` is applied.
+    maxPixels?: number // The maximum width * height of the rendered image.
+    maxNodes?: number // The maximum number of elements, counted with every `<use>` expanded.
+    maxUseDepth?: number // The maximum nesting depth of `<use>` references.
+    maxDecompressedBytes?: number // Default: 100 MiB, the maximum size of a decompressed SVGZ input.
+    renderTimeout?: number // The maximum time in milliseconds spent parsing, and then again rendering.
+  }
+  resourcesDir?: string // The directory used to resolve relative and `file://` image hrefs. Default: the file's directory with `Resvg.fromFile()`.
+  resourcesSandbox?: boolean // Default: true, refuse local images outside of `resourcesDir` (via `..` or symlinks).
+  /**
+   * Resolve http(s) image hrefs while parsing, including images nested in patterns, masks and clip paths.
+   * Return `null` to leave the image unresolved, it can still be resolved later by `resolveImage()`.
+   * Returning data that is not a supported image throws `ERR_UNSUPPORTED_IMAGE`, AVIF throws `ERR_UNDECODABLE_IMAGE`.
+   */
+  imageResolver?: (href: string) => Buffer | null
 }
+
+export type ResvgRenderAsyncOptions = Omit<ResvgRenderOptions, 'imageResolver'> & {
+  /** Same as `ResvgRenderOptions.imageResolver`, but images are fetched concurrently. */
+  imageResolver?: (href: string) => Promise<Buffer | null>
+}
+export type ResvgErrorCode =
+  | 'ERR_INVALID_COLOR'
+  | 'ERR_USVG_PARSE'
+  | 'ERR_PNG_ENCODING'
+  | 'ERR_INVALID_UTF8'
+  | 'ERR_ZERO_SIZED'
+  | 'ERR_ALLOCATION_FAILED'
+  | 'ERR_RENDER_PANIC'
+  | 'ERR_INVALID_OPTIONS'
+  | 'ERR_INVALID_LOG_LEVEL'
+  | 'ERR_READ_FILE'
+  | 'ERR_INVALID_INPUT'
+  | 'ERR_UNSUPPORTED_IMAGE'
+  | 'ERR_UNDECODABLE_IMAGE'
+  | 'ERR_IMAGE_DECODING'
+  | 'ERR_IMAGE_BYTES_LIMIT'
+  | 'ERR_IMAGE_PIXELS_LIMIT'
+  | 'ERR_IMAGE_COUNT_LIMIT'
+  | 'ERR_WIDTH_LIMIT'
+  | 'ERR_HEIGHT_LIMIT'
+  | 'ERR_PIXELS_LIMIT'
+  | 'ERR_DECOMPRESSED_SIZE_LIMIT'
+  | 'ERR_NODE_COUNT_LIMIT'
+  | 'ERR_USE_DEPTH_LIMIT'
+  | 'ERR_RENDER_TIMEOUT'
+  | 'ERR_FONT_NOT_FOUND'
+  | 'ERR_ELEMENT_NOT_FOUND'
+  | 'ERR_EMPTY_ELEMENT'
+  | 'ERR_INVALID_CROP'
+  | 'ERR_INVALID_REGION'
+  | 'ERR_INVALID_LEVELS'
+
+/** The errors thrown by resvg-js. Errors thrown by callbacks, e.g. `imageResolver`, are rethrown unchanged, without a `code`. */
+export interface ResvgError extends Error {
+  code: ResvgErrorCode
+  /** The 1-based line of an XML parsing error (`ERR_USVG_PARSE`). */
+  line?: number
+  /** The 1-based column of an XML parsing error (`ERR_USVG_PARSE`). */
+  column?: number
+}
+
 export class BBox {
   x: number
   y: number
@@ -47,21 +124,269 @@ export class BBox {
   height: number
 }
 
+export type CropByBBoxOptions = {
+  padding?: number | { top?: number; right?: number; bottom?: number; left?: number } // Default: 0, the bleed area in output pixels.
+  square?: boolean // Default: false, make the crop area square using the larger dimension.
+  aspectRatio?: number // Grow the crop area to this width / height ratio, like `square` for 1.
+  relative?: boolean // Default: false, position the bbox relative to the top-left corner of the current crop.
+  normalize?: boolean // Default: false, move the content so that the cropped viewbox starts at 0,0, e.g. for `toString()`.
+}
+
+export type ElementBBoxOptions = {
+  /**
+   * Default: 'fill', the geometry of the shapes like `SVGGraphicsElement.getBBox()`.
+   * 'stroke' includes the strokes, 'visual' only the painted area clipped by clip paths, masks and the canvas.
+   * 'ink' is 'visual' with the exact stroke joins, miter limit and dashes, and the filter regions, e.g. drop shadows.
+   */
+  kind?: 'fill' | 'stroke' | 'visual' | 'ink'
+}
+
+export interface ElementBBox {
+  /** In user space, like `getElementBBox()`. */
+  user: BBox
+  /** In the pixels of `render()`, after `fitTo` and the `crop` option. */
+  pixel: BBox
+}
+
+export interface TextChunk {
+  /** The id of the `<text>` element, empty if it has none. */
+  id: string
+  /** The text content of this chunk. */
+  text: string
+  /** The font families of the first span, comma separated. */
+  fontFamily: string
+  /** The font size of the first span. */
+  fontSize: number
+  /** The fill of the first span, e.g. `#ff0000` or `url(#gradient)`. */
+  fill?: string
+  /** The bounding box of this chunk in user space. */
+  bbox?: BBox
+}
+
+/** A warning logged while parsing or rendering the SVG. */
+export interface Warning {
+  level: 'warn' | 'error'
+  /** The module that logged it, e.g. `usvg_parser::image`. */
+  target: string
+  message: string
+  /** The id of the element the message refers to, when it mentions one. */
+  elementId?: string
+}
+
+export type RenderElementOptions = {
+  fitTo?: ResvgRenderOptions['fitTo'] // Default: { mode: 'original' }, applied to the bounding box including the padding.
+  padding?: number // Default: 0, extra space around the bounding box in user units, e.g. to include strokes.
+}
+
+export type RenderSymbolsOptions = {
+  fitTo?: ResvgRenderOptions['fitTo'] // Default: { mode: 'original' }, applied to every symbol.
+  format?: 'rgba' | 'png' // Default: 'rgba', a `RenderedImage`. 'png' returns PNG encoded Buffers instead.
+}
+
+export interface SymbolImage<T = RenderedImage | Buffer> {
+  /** The id of the `<symbol>` or group. */
+  id: string
+  image: T
+}
+
+// Throws `ERR_INVALID_REGION` when the region is empty, or when both options are set.
+export type RenderRegionOptions = {
+  scale?: number // Default: 1, the number of pixels per user unit.
+  outputWidth?: number // The width of the image in pixels, the height follows the aspect ratio of the region.
+}
+
+export type RenderTilesOptions = {
+  tileSize?: number // Default: 512, the tiles of the last column and row can be smaller.
+  fitTo?: ResvgRenderOptions['fitTo'] // Default: the `fitTo` option, the size of the whole image.
+  format?: 'rgba' | 'png' // Default: 'rgba', a `RenderedImage`. 'png' returns PNG encoded Buffers instead.
+}
+
+export interface Tile<T = RenderedImage | Buffer> {
+  /** The position of the tile in the whole image, in pixels. */
+  x: number
+  y: number
+  width: number
+  height: number
+  image: T
+}
+
+export type TilePyramidOptions = {
+  tileSize?: number // Default: 254, the size of the tiles without the overlap.
+  overlap?: number // Default: 1, the pixels shared with the neighbouring tiles on each side.
+  format?: 'png' | 'rgba' // Default: 'png', PNG encoded Buffers. 'rgba' returns `RenderedImage`s instead.
+  minLevel?: number // Default: 0, the first exported level. The image is 1x1 at level 0.
+  maxLevel?: number // Default: the level of the full size image, larger values are clamped to it.
+  // Throws `ERR_INVALID_LEVELS` when `minLevel` is greater than the clamped `maxLevel`.
+}
+
+export interface PyramidTile<T = RenderedImage | Buffer> {
+  level: number
+  column: number
+  row: number
+  /** The position of the tile in its level, including the overlap. */
+  x: number
+  y: number
+  width: number
+  height: number
+  image: T
+}
+
+export interface TilePyramid<T = RenderedImage | Buffer> {
+  /**
+   * The `.dzi` descriptor, tiles are expected at `{name}_files/{level}/{column}_{row}.png`.
+   * Only set with `format: 'png'`, raw RGBA tiles cannot be loaded by DZI viewers.
+   */
+  dzi: T extends Buffer ? string : undefined
+  /** The size of the full size image, at the last level of the pyramid. */
+  width: number
+  height: number
+  tileSize: number
+  overlap: number
+  minLevel: number
+  maxLevel: number
+  tiles: Array<PyramidTile<T>>
+}
+
+export type PngStreamOptions = {
+  stripHeight?: number // Default: 256, the number of rows rendered at once.
+  fitTo?: ResvgRenderOptions['fitTo'] // Default: the `fitTo` option, the size of the whole image.
+}
+
+export type PackSpriteSheetOptions = ResvgRenderOptions & {
+  padding?: number // Default: 0, the space between two frames in pixels.
+  maxWidth?: number // Default: about the square root of the total area, frames wrap to a new row past it.
+}
+
+/** The position of an SVG in the sprite sheet, in pixels. */
+export interface SpriteFrame {
+  x: number
+  y: number
+  width: number
+  height: number
+}
+
+export interface SpriteSheet {
+  image: RenderedImage
+  /** The position of every SVG, in the order they were passed. */
+  frames: Array<SpriteFrame>
+}
+
+export type MeasureTextOptions = {
+  font?: ResvgRenderOptions['font']
+  fontFamily?: string // A CSS-like list of font families, e.g. "Pacifico, serif". Default: the default font family
+  fontSize?: number // Default: the default font size
+  weight?: number // Default: 400
+  letterSpacing?: number // Default: 0
+  lenient?: boolean // Default: false, fall back to the default options instead of throwing when the options are invalid.
+}
+
+export interface GlyphPosition {
+  /** The byte offset of the first character of the cluster. */
+  cluster: number
+  x: number
+  /** The horizontal advance, including letter spacing. */
+  advance: number
+}
+
+export class TextMetrics {
+  /** Get the advance width of the text */
+  get width(): number
+  /** Get the font ascent above the baseline */
+  get ascent(): number
+  /** Get the font descent below the baseline, as a positive number */
+  get descent(): number
+  /** Get the position of every glyph cluster */
+  get glyphs(): Array<GlyphPosition>
+}
+
+export type LogLevel = 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace'
+
+/**
+ * Forward the log records to `callback` instead of stderr, pass `null` to restore stderr.
+ * `level` changes the log level like `setLogLevel()`.
+ */
+export function setLogger(
+  callback: ((level: Exclude<LogLevel, 'off'>, target: string, message: string) => void) | null,
+  level?: LogLevel,
+): void
+
+/** Change the log level of the instances without a `logLevel` option, at runtime. */
+export function setLogLevel(level: LogLevel): void
+
+/** Measure a single line of text with the same fonts and shaping used to render SVG text */
+export function measureText(text: string, options?: MeasureTextOptions | null): TextMetrics
+
+/**
+ * Render several SVGs into one sprite sheet, packed in rows.
+ * `frames` can be serialized with `JSON.stringify()`.
+ */
+export function packSpriteSheet(
+  svgs: Array<string | Buffer>,
+  options?: Omit<PackSpriteSheetOptions, 'imageResolver'> | null,
+): SpriteSheet
+
 export function renderAsync(
   svg: string | Buffer,
-  options?: ResvgRenderOptions | null,
+  options?: ResvgRenderAsyncOptions | null,
+  signal?: AbortSignal | null,
+): Promise<RenderedImage>
+
+/** Read and render an SVG or SVGZ file, relative image hrefs default to resolving against its directory. */
+export function renderFileAsync(
+  path: string,
+  options?: ResvgRenderAsyncOptions | null,
   signal?: AbortSignal | null,
 ): Promise<RenderedImage>
 export class Resvg {
+  /** `svg` can be a SVGZ (gzip-compressed SVG) Buffer. */
   constructor(svg: Buffer | string, options?: ResvgRenderOptions | null)
+  /** Load an SVG or SVGZ file, relative image hrefs default to resolving against its directory. */
+  static fromFile(path: string, options?: ResvgRenderOptions | null): Resvg
   toString(): string
   render(): RenderedImage
+  /**
+   * Render only the element with `id` and its subtree, e.g. a `<g>` or a `<symbol>` of an icon set.
+   * Everything else is hidden, and the viewbox is set to the element's bounding box.
+   * Throws `ERR_ELEMENT_NOT_FOUND` if no element has this id.
+   */
+  renderElement(id: string, options?: RenderElementOptions | null): RenderedImage
+  /**
+   * Render every `<symbol>` with an id, then every top-level group with an id, e.g. the icons of a sprite.
+   * A symbol with a `viewBox` is rendered at the size of its `viewBox`, anything else is cropped to its bounding box.
+   */
+  renderSymbols(options?: RenderSymbolsOptions & { format?: 'rgba' }): Array<SymbolImage<RenderedImage>>
+  renderSymbols(options: RenderSymbolsOptions & { format: 'png' }): Array<SymbolImage<Buffer>>
+  /**
+   * Render a rectangle of user space at a given scale, e.g. for deep-zoom viewers.
+   * The viewbox, `fitTo` and `crop` are ignored and left unchanged, unlike `cropByBBox()`.
+   */
+  renderRegion(region: { x: number; y: number; width: number; height: number }, options?: RenderRegionOptions | null): RenderedImage
+  /**
+   * Render the image tile by tile, row by row, e.g. for outputs too large for a single `RenderedImage`.
+   * Only the current tile is held in memory. The `crop` option is applied, `trim` is ignored.
+   */
+  renderTiles(options: RenderTilesOptions & { format?: 'rgba' }, callback: (tile: Tile<RenderedImage>) => void): void
+  renderTiles(options: RenderTilesOptions & { format: 'png' }, callback: (tile: Tile<Buffer>) => void): void
+  /**
+   * Render every level of a Deep Zoom (DZI) pyramid from the vector tree, e.g. for OpenSeadragon.
+   * Each level is rendered directly at its scale rather than downsampled. The `fitTo` and `crop` options
+   * give the full size image.
+   */
+  exportTilePyramid(options?: TilePyramidOptions & { format?: 'png' }): TilePyramid<Buffer>
+  exportTilePyramid(options: TilePyramidOptions & { format: 'rgba' }): TilePyramid<RenderedImage>
+  /**
+   * Encode the image as a PNG without holding the whole image in memory,
+   * `callback` receives the chunks of the file in order, e.g. to write them to a stream.
+   */
+  renderPngStream(options: PngStreamOptions | null | undefined, callback: (chunk: Buffer) => void): void
   /**
    * Calculate a maximum bounding box of all visible elements in this SVG.
    *
-   * Note: path bounding box are approx values.
+   * Note: path bounding box are approx values, unless `kind` is 'ink'.
+   * Use `{ kind: 'ink' }` to include filters like drop shadows, e.g. for `cropByBBox()`.
+   * `kind` defaults to 'visual'.
    */
-  innerBBox(): BBox | undefined
+  innerBBox(options?: ElementBBoxOptions | null): BBox | undefined
   /**
    * Calculate a maximum bounding box of all visible elements in this SVG.
    * This will first apply transform.
@@ -69,15 +394,40 @@ export class Resvg {
    */
   getBBox(): BBox | undefined
   /**
-   * Use a given `BBox` to crop the svg. Currently this method simply changes
-   * the viewbox/size of the svg and do not move the elements for simplicity
+   * Calculate the bounding box of the element with `id` in user space.
+   * Returns `undefined` if it is empty, and throws `ERR_ELEMENT_NOT_FOUND` if no element has this id.
+   */
+  getElementBBox(id: string, options?: ElementBBoxOptions | null): BBox | undefined
+  /** Calculate the bounding box of every element with an id, e.g. to build image maps. */
+  elementBBoxes(options?: ElementBBoxOptions | null): Record<string, ElementBBox>
+  /**
+   * Get the ids of the elements whose painted fill or stroke covers the pixel `(x, y)` of `render()`,
+   * from the outermost to the innermost, e.g. to find the element under the mouse.
+   * Transforms, clip paths, `visibility`, `fitTo` and `crop` are taken into account.
+   */
+  elementsAtPoint(x: number, y: number): string[]
+  /**
+   * Use a given `BBox` to crop the svg. By default this method simply changes
+   * the viewbox/size of the svg, `{ normalize: true }` also moves the elements to 0,0
    * # Arguments
    * `bbox` - The bounding box to crop to
    * `padding` - Optional bleed area around the crop box (default: 0.0)
    * `square` - Optional flag to make the crop area square using the larger dimension (default: false)
+   * `relative` - Optional flag to position `bbox` relative to the top-left corner of the current crop (default: false)
    */
   // cropByBBox(bbox: BBox): void
-  cropByBBox(bbox: BBox, padding?: number, square?: boolean): void
+  cropByBBox(bbox: BBox, padding?: number, square?: boolean, relative?: boolean): void
+  cropByBBox(bbox: BBox, options: CropByBBoxOptions): void
+  /** Restore the viewbox and size of the SVG before `cropByBBox()`, to crop it again. */
+  resetCrop(): void
+  /** Get the area cropped by `cropByBBox()` in user space, including the padding, or `undefined` if not cropped. */
+  getCrop(): BBox | undefined
+
+  /** Get all text chunks with their font, fill and bounding box in user space. */
+  textContent(): Array<TextChunk>
+
+  /** Get the warnings logged while parsing, rendering and resolving images. */
+  warnings(): Array<Warning>
 
   imagesToResolve(): Array<string>
   resolveImage(href: string, buffer: Buffer): void
@@ -100,4 +450,10 @@ export class RenderedImage {
 
   /** Get the PNG height */
   get height(): number
+
+  /**
+   * Get the smallest rectangle containing every pixel whose alpha is above `threshold` (default: 0),
+   * or `undefined` if there is none.
+   */
+  opaqueBounds(threshold?: number): BBox | undefined
 }
@@ -1,4 +1,16 @@
-const { render: _render, renderAsync: _renderAsync, Resvg: _Resvg } = require('./js-binding.js')
+const {
+  render: _render,
+  renderAsync: _renderAsync,
+  renderFileAsync: _renderFileAsync,
+  measureText: _measureText,
+  packSpriteSheet: _packSpriteSheet,
+  setLogger,
+  setLogLevel,
+  Resvg: _Resvg,
+} = require('./js-binding.js')
+
+module.exports.setLogger = setLogger
+module.exports.setLogLevel = setLogLevel
 
 module.exports.render = function render(svg, options) {
   if (options) {
@@ -9,14 +21,84 @@ module.exports.render = function render(svg, options) {
 
 module.exports.renderAsync = function renderAsync(svg, options, signal) {
   if (options) {
-    return _renderAsync(svg, JSON.stringify(options), signal)
+    return _renderAsync(svg, JSON.stringify(options), signal, options.imageResolver)
   }
   return _renderAsync(svg, null, signal)
 }
 
+module.exports.renderFileAsync = function renderFileAsync(path, options, signal) {
+  if (options) {
+    return _renderFileAsync(path, JSON.stringify(options), signal, options.imageResolver)
+  }
+  return _renderFileAsync(path, null, signal)
+}
+
+module.exports.packSpriteSheet = function packSpriteSheet(svgs, options) {
+  if (options) {
+    const { padding, maxWidth, ...renderOptions } = options
+    return _packSpriteSheet(svgs, JSON.stringify(renderOptions), JSON.stringify({ padding, maxWidth }))
+  }
+  return _packSpriteSheet(svgs)
+}
+
+module.exports.measureText = function measureText(text, options) {
+  if (options) {
+    return _measureText(text, JSON.stringify(options))
+  }
+  return _measureText(text)
+}
+
 module.exports.Resvg = class Resvg extends _Resvg {
   constructor(svg, options) {
-    super(svg, JSON.stringify(options))
+    super(svg, JSON.stringify(options), options && options.imageResolver)
+  }
+
+  innerBBox(options) {
+    return super.innerBBox(JSON.stringify(options))
+  }
+
+  getElementBBox(id, options) {
+    return super.getElementBBox(id, JSON.stringify(options))
+  }
+
+  elementBBoxes(options) {
+    return super.elementBBoxes(JSON.stringify(options))
+  }
+
+  cropByBBox(bbox, padding, square, relative) {
+    if (typeof padding === 'object' && padding !== null) {
+      return super.cropByBBoxWithOptions(bbox, JSON.stringify(padding))
+    }
+    return super.cropByBBox(bbox, padding, square, relative)
+  }
+
+  renderElement(id, options) {
+    return super.renderElement(id, JSON.stringify(options))
+  }
+
+  renderSymbols(options) {
+    return super.renderSymbols(JSON.stringify(options))
+  }
+
+  renderRegion(region, options) {
+    return super.renderRegion(region.x, region.y, region.width, region.height, JSON.stringify(options))
+  }
+
+  renderTiles(options, callback) {
+    return super.renderTiles(JSON.stringify(options), callback)
+  }
+
+  exportTilePyramid(options) {
+    return super.exportTilePyramid(JSON.stringify(options))
+  }
+
+  renderPngStream(options, callback) {
+    return super.renderPngStream(JSON.stringify(options), callback)
+  }
+
+  static fromFile(path, options) {
+    const resvg = _Resvg.fromFile(path, JSON.stringify(options), options && options.imageResolver)
+    return Object.setPrototypeOf(resvg, Resvg.prototype)
   }
 }
 
@@ -16,36 +16,75 @@ export declare class RenderedImage {
   get width(): number
   /** Get the PNG height */
   get height(): number
+  /** Get the smallest rectangle containing every pixel whose alpha is above `threshold` (default: 0) */
+  opaqueBounds(threshold?: number | undefined | null): BBox | undefined
 }
 
 export declare class Resvg {
-  constructor(svg: string | Buffer, options?: string | undefined | null)
+  constructor(svg: string | Buffer, options?: string | undefined | null, imageResolver?: ((arg: string) => Buffer | null) | undefined | null)
+  /** Load an SVG or SVGZ file, relative image hrefs are resolved against its directory by default */
+  static fromFile(path: string, options?: string | undefined | null, imageResolver?: ((arg: string) => Buffer | null) | undefined | null): Resvg
   /** Renders an SVG in Node.js */
   render(): RenderedImage
+  /** Renders only the element with `id` and its subtree, cropped to its bounding box */
+  renderElement(id: string, options?: string | undefined | null): RenderedImage
+  /** Renders every `<symbol>` and top-level group with an id, e.g. the icons of a sprite */
+  renderSymbols(options?: string | undefined | null): Array<SymbolImage>
+  /** Renders the `region` of user space at a given scale, without changing the viewbox */
+  renderRegion(x: number, y: number, width: number, height: number, options?: string | undefined | null): RenderedImage
+  /** Renders the image tile by tile and passes every tile to `callback`, row by row */
+  renderTiles(options: string | undefined | null, callback: ((arg: Tile) => void)): void
+  /** Renders every level of a Deep Zoom pyramid from the vector tree, e.g. for OpenSeadragon */
+  exportTilePyramid(options?: string | undefined | null): TilePyramid
+  /** Encodes the image as a PNG strip by strip, without holding the whole image in memory */
+  renderPngStream(options: string | undefined | null, callback: ((arg: Buffer) => void)): void
   /** Output usvg-simplified SVG string */
   toString(): string
   /**
    * Calculate a maximum bounding box of all visible elements in this SVG.
    *
-   * Note: path bounding box are approx values.
+   * Note: path bounding box are approx values, unless `kind` is `ink`.
    */
-  innerBBox(): BBox | undefined
+  innerBBox(options?: string | undefined | null): BBox | undefined
   /**
    * Calculate a maximum bounding box of all visible elements in this SVG.
    * This will first apply transform.
    * Similar to `SVGGraphicsElement.getBBox()` DOM API.
    */
   getBBox(): BBox | undefined
+  /** Calculate the bounding box of the element with `id` in user space. */
+  getElementBBox(id: string, options?: string | undefined | null): BBox | undefined
+  /** Calculate the bounding box of every element with an id, in user space and in output pixels. */
+  elementBBoxes(options?: string | undefined | null): Record<string, ElementBBox>
   /**
-   * Use a given `BBox` to crop the svg. Currently this method simply changes
-   * the viewbox/size of the svg and do not move the elements for simplicity
+   * Get the ids of the elements painted at the pixel `(x, y)` of the rendered image,
+   * from the outermost to the innermost.
+   */
+  elementsAtPoint(x: number, y: number): Array<string>
+  /**
+   * Use a given `BBox` to crop the svg. By default this method simply changes
+   * the viewbox/size of the svg, `{ normalize: true }` also moves the elements to 0,0
    *
    * # Arguments
    * * `bbox` - The bounding box to crop to
    * * `padding` - Optional bleed area around the crop box (default: 0.0)
    * * `square` - Optional flag to make the crop area square using the larger dimension (default: false)
+   * * `relative` - Optional flag to position `bbox` relative to the current crop instead of the user space (default: false)
+   */
+  cropByBBox(bbox: BBox, padding?: number | undefined | null, square?: boolean | undefined | null, relative?: boolean | undefined | null): void
+  /**
+   * Use a given `BBox` to crop the svg, with per side padding, an aspect ratio,
+   * or moving the content to 0,0
    */
-  cropByBBox(bbox: BBox, padding?: number | undefined | null, square?: boolean | undefined | null): void
+  cropByBBoxWithOptions(bbox: BBox, options?: string | undefined | null): void
+  /** Restore the viewbox and size of the SVG before `cropByBBox()` */
+  resetCrop(): void
+  /** Get the area cropped by `cropByBBox()` in user space, including the padding */
+  getCrop(): BBox | undefined
+  /** Get all text chunks with their font, fill and bounding box in user space. */
+  textContent(): Array<TextChunk>
+  /** Get the warnings logged while parsing, rendering and resolving images. */
+  warnings(): Array<Warning>
   imagesToResolve(): Array<string>
   resolveImage(href: string, buffer: Buffer): void
   /** Get the SVG width */
@@ -54,4 +93,139 @@ export declare class Resvg {
   get height(): number
 }
 
-export declare function renderAsync(svg: string | Buffer, options?: string | undefined | null, signal?: AbortSignal | undefined | null): Promise<RenderedImage>
+export declare class TextMetrics {
+  /** Get the advance width of the text */
+  get width(): number
+  /** Get the font ascent above the baseline */
+  get ascent(): number
+  /** Get the font descent below the baseline, as a positive number */
+  get descent(): number
+  /** Get the position of every glyph cluster */
+  get glyphs(): Array<GlyphPosition>
+}
+
+/** The bounding box of an element returned by `elementBBoxes()`. */
+export interface ElementBBox {
+  /** In user space, like `getElementBBox()`. */
+  user: BBox
+  /** In the pixels of `render()`, after `fitTo` and the `crop` option. */
+  pixel: BBox
+}
+
+/** The position of a glyph cluster on the baseline, as laid out by usvg. */
+export interface GlyphPosition {
+  /** The byte offset of the first character of the cluster. */
+  cluster: number
+  x: number
+  /** The horizontal advance, including letter spacing. */
+  advance: number
+}
+
+/** Renders several SVGs into one sprite sheet, with the position of every SVG */
+export declare function packSpriteSheet(svgs: Array<string | Buffer>, options?: string | undefined | null, packOptions?: string | undefined | null): SpriteSheet
+
+/** Measure a single line of text with the same fonts and shaping used to render SVG text */
+export declare function measureText(text: string, options?: string | undefined | null): TextMetrics
+
+/** A text chunk of the SVG, captured before the text is converted to paths. */
+export interface TextChunk {
+  /** The id of the `<text>` element, empty if it has none. */
+  id: string
+  /** The text content of this chunk. */
+  text: string
+  /** The font families of the first span, comma separated. */
+  fontFamily: string
+  /** The font size of the first span. */
+  fontSize: number
+  /** The fill of the first span, e.g. `#ff0000` or `url(#gradient)`. */
+  fill?: string
+  /**
+   * The bounding box of this chunk in user space.
+   *
+   * `None` when no font could be found to shape the text.
+   */
+  bbox?: BBox
+}
+
+/** A warning logged while parsing or rendering the SVG. */
+export interface Warning {
+  /** `warn` or `error`. */
+  level: string
+  /** The module that logged it, e.g. `usvg_parser::image`. */
+  target: string
+  message: string
+  /** The id of the element the message refers to, when it mentions one. */
+  elementId?: string
+}
+
+/** The position of an SVG in the sprite sheet. */
+export interface SpriteFrame {
+  x: number
+  y: number
+  width: number
+  height: number
+}
+
+/** The sprite sheet built by `packSpriteSheet()`. */
+export interface SpriteSheet {
+  image: RenderedImage
+  /** The position of every SVG, in the order they were passed. */
+  frames: Array<SpriteFrame>
+}
+
+/** A symbol rendered by `renderSymbols()`. */
+export interface SymbolImage {
+  id: string
+  /** A `RenderedImage`, or a PNG buffer with `format: 'png'`. */
+  image: RenderedImage | Buffer
+}
+
+/** A tile exported by `exportTilePyramid()`. */
+export interface PyramidTile {
+  level: number
+  column: number
+  row: number
+  /** The position of the tile in its level, including the overlap. */
+  x: number
+  y: number
+  width: number
+  height: number
+  /** A PNG buffer, or a `RenderedImage` with `format: 'rgba'`. */
+  image: RenderedImage | Buffer
+}
+
+/** The Deep Zoom pyramid built by `exportTilePyramid()`. */
+export interface TilePyramid {
+  /** The `.dzi` descriptor, only set for PNG tiles. */
+  dzi?: string
+  /** The size of the full size image, at the last level of the pyramid. */
+  width: number
+  height: number
+  tileSize: number
+  overlap: number
+  minLevel: number
+  maxLevel: number
+  tiles: Array<PyramidTile>
+}
+
+/** A tile rendered by `renderTiles()`. */
+export interface Tile {
+  /** The position of the tile in the whole image. */
+  x: number
+  y: number
+  width: number
+  height: number
+  /** A `RenderedImage`, or a PNG buffer with `format: 'png'`. */
+  image: RenderedImage | Buffer
+}
+
+/** Forward the log records to `callback` instead of stderr, pass `null` to restore stderr */
+export declare function setLogger(callback?: ((arg0: string, arg1: string, arg2: string) => void) | undefined | null, level?: string | undefined | null): void
+
+/** Change the log level of the instances without a `logLevel` option */
+export declare function setLogLevel(level: string): void
+
+export declare function renderAsync(svg: string | Buffer, options?: string | undefined | null, signal?: AbortSignal | undefined | null, imageResolver?: ((arg: string) => Promise<Buffer | null>) | undefined | null): Promise<RenderedImage>
+
+/** Read and render an SVG or SVGZ file in the libuv thread pool */
+export declare function renderFileAsync(path: string, options?: string | undefined | null, signal?: AbortSignal | undefined | null, imageResolver?: ((arg: string) => Promise<Buffer | null>) | undefined | null): Promise<RenderedImage>
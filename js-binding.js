@@ -579,4 +579,10 @@ module.exports = nativeBinding
 module.exports.BBox = nativeBinding.BBox
 module.exports.RenderedImage = nativeBinding.RenderedImage
 module.exports.Resvg = nativeBinding.Resvg
+module.exports.TextMetrics = nativeBinding.TextMetrics
+module.exports.measureText = nativeBinding.measureText
+module.exports.packSpriteSheet = nativeBinding.packSpriteSheet
 module.exports.renderAsync = nativeBinding.renderAsync
+module.exports.renderFileAsync = nativeBinding.renderFileAsync
+module.exports.setLogger = nativeBinding.setLogger
+module.exports.setLogLevel = nativeBinding.setLogLevel
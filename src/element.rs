@@ -0,0 +1,65 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use resvg::usvg::{self, NodeExt};
+
+use crate::error::Error;
+
+/// Builds a tree that only renders the element with `id` and its subtree.
+///
+/// The ancestors are kept, so that their transforms, opacity, clip paths and masks still apply,
+/// everything else is removed. The viewbox is set to the element's bounding box,
+/// which does not include strokes, grown by `padding` on every side.
+pub(crate) fn element_tree(tree: &usvg::Tree, id: &str, padding: f32) -> Result<usvg::Tree, Error> {
+    let root = tree.root.make_deep_copy();
+    let node = root
+        .descendants()
+        .find(|node| !id.is_empty() && &*node.id() == id)
+        .ok_or_else(|| Error::ElementNotFound(id.to_string()))?;
+
+    let mut current = node.clone();
+    while let Some(parent) = current.parent() {
+        let siblings: Vec<usvg::Node> = parent.children().filter(|n| *n != current).collect();
+        for sibling in siblings {
+            sibling.detach();
+        }
+        current = parent;
+    }
+    crop_to_bbox(root, &node, id, padding)
+}
+
+/// Sets the viewbox of the tree with `root` to the bounding box of `node`, grown by `padding`.
+pub(crate) fn crop_to_bbox(
+    root: usvg::Node,
+    node: &usvg::Node,
+    id: &str,
+    padding: f32,
+) -> Result<usvg::Tree, Error> {
+    // Reject NaN, Infinity, and negative values, like `cropByBBox()`.
+    let padding = if padding.is_finite() && padding >= 0.0 {
+        padding
+    } else {
+        0.0
+    };
+    let rect = node
+        .calculate_bbox()
+        .and_then(|bbox| {
+            usvg::NonZeroRect::from_xywh(
+                bbox.x() - padding,
+                bbox.y() - padding,
+                bbox.width() + padding * 2.0,
+                bbox.height() + padding * 2.0,
+            )
+        })
+        .ok_or_else(|| Error::EmptyElement(id.to_string()))?;
+
+    Ok(usvg::Tree {
+        size: rect.size(),
+        view_box: usvg::ViewBox {
+            rect,
+            aspect: usvg::AspectRatio::default(),
+        },
+        root,
+    })
+}
@@ -2,6 +2,11 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+#[cfg(not(target_arch = "wasm32"))]
+use napi::{
+    Env, JsError,
+    bindgen_prelude::{FromNapiValue, Object, Unknown},
+};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -27,23 +32,192 @@ pub enum Error {
     #[error("Rendering failed due to an resvg panic, {0}")]
     RenderPanic(String),
 
+    #[error("Invalid options, {0}")]
+    InvalidOptions(serde_json::Error),
+
+    #[error("Invalid log level '{0}', expected off, error, warn, info, debug or trace")]
+    InvalidLogLevel(String),
+
+    #[error("Failed to read '{0}' cause {1}")]
+    ReadFile(String, std::io::Error),
+
     #[error("Input must be string or Uint8Array")]
     InvalidInput,
 
-    #[error("Unsupported image types (currently resvg only supports PNG, JPEG and GIF)")]
+    #[error(
+        "Unsupported image types (currently resvg-js only supports PNG, JPEG, GIF, WebP, BMP, ICO and SVG)"
+    )]
     UnsupportedImage,
+
+    #[error("The image resolved for '{0}' is not a supported image type")]
+    UnsupportedResolvedImage(String),
+
+    #[error("{0} is detected but not supported by this build")]
+    UndecodableImage(&'static str),
+
+    #[error("Failed to decode image cause {0}")]
+    ImageDecoding(String),
+
+    #[error("Image '{0}' exceeds the maxImageBytes limit ({1} > {2} bytes)")]
+    ImageBytesLimit(String, usize, u64),
+
+    #[error("Image '{0}' exceeds the maxImagePixels limit ({1}x{2} > {3} pixels)")]
+    ImagePixelsLimit(String, u32, u32, u64),
+
+    #[error("The number of images exceeds the maxImages limit ({0})")]
+    ImageCountLimit(u32),
+
+    #[error("Output width {0} exceeds the maxWidth limit ({1})")]
+    WidthLimit(u32, u32),
+
+    #[error("Output height {0} exceeds the maxHeight limit ({1})")]
+    HeightLimit(u32, u32),
+
+    #[error("Output size {0}x{1} exceeds the maxPixels limit ({2} pixels)")]
+    PixelsLimit(u32, u32, u64),
+
+    #[error("The decompressed SVGZ exceeds the maxDecompressedBytes limit ({0} bytes)")]
+    DecompressedSizeLimit(u64),
+
+    #[error("The number of elements exceeds the maxNodes limit ({0})")]
+    NodeCountLimit(u64),
+
+    #[error("The nesting depth of <use> elements exceeds the maxUseDepth limit ({0})")]
+    UseDepthLimit(u32),
+
+    #[error("Timed out after the renderTimeout limit ({0}ms)")]
+    RenderTimeout(u64),
+
+    #[error("No font found for font-family '{0}'")]
+    FontNotFound(String),
+
+    #[error("Element '{0}' not found")]
+    ElementNotFound(String),
+
+    #[error("Element '{0}' has an empty bounding box")]
+    EmptyElement(String),
+
+    #[error("Invalid crop, {0}")]
+    InvalidCrop(String),
+
+    #[error("Invalid region, {0}")]
+    InvalidRegion(String),
+
+    #[error("Invalid levels, minLevel {0} is greater than maxLevel {1}")]
+    InvalidLevels(u32, u32),
+
+    /// An error thrown by a javascript callback, rethrown unchanged.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error(transparent)]
+    Napi(#[from] napi::Error),
+}
+
+impl Error {
+    /// The stable `code` property of the javascript error.
+    ///
+    /// `None` for the errors thrown by javascript callbacks, they are rethrown unchanged.
+    pub fn code(&self) -> Option<&'static str> {
+        Some(match self {
+            Error::Svg(_) => "ERR_INVALID_COLOR",
+            Error::USvg(_) => "ERR_USVG_PARSE",
+            Error::Encoding(_) => "ERR_PNG_ENCODING",
+            Error::Utf8(_) => "ERR_INVALID_UTF8",
+            Error::ZeroSized => "ERR_ZERO_SIZED",
+            Error::AllocationFailed => "ERR_ALLOCATION_FAILED",
+            Error::RenderPanic(_) => "ERR_RENDER_PANIC",
+            Error::InvalidOptions(_) => "ERR_INVALID_OPTIONS",
+            Error::InvalidLogLevel(_) => "ERR_INVALID_LOG_LEVEL",
+            Error::ReadFile(..) => "ERR_READ_FILE",
+            Error::InvalidInput => "ERR_INVALID_INPUT",
+            Error::UnsupportedImage => "ERR_UNSUPPORTED_IMAGE",
+            Error::UnsupportedResolvedImage(_) => "ERR_UNSUPPORTED_IMAGE",
+            Error::UndecodableImage(_) => "ERR_UNDECODABLE_IMAGE",
+            Error::ImageDecoding(_) => "ERR_IMAGE_DECODING",
+            Error::ImageBytesLimit(..) => "ERR_IMAGE_BYTES_LIMIT",
+            Error::ImagePixelsLimit(..) => "ERR_IMAGE_PIXELS_LIMIT",
+            Error::ImageCountLimit(_) => "ERR_IMAGE_COUNT_LIMIT",
+            Error::WidthLimit(..) => "ERR_WIDTH_LIMIT",
+            Error::HeightLimit(..) => "ERR_HEIGHT_LIMIT",
+            Error::PixelsLimit(..) => "ERR_PIXELS_LIMIT",
+            Error::DecompressedSizeLimit(_) => "ERR_DECOMPRESSED_SIZE_LIMIT",
+            Error::NodeCountLimit(_) => "ERR_NODE_COUNT_LIMIT",
+            Error::UseDepthLimit(_) => "ERR_USE_DEPTH_LIMIT",
+            Error::RenderTimeout(_) => "ERR_RENDER_TIMEOUT",
+            Error::FontNotFound(_) => "ERR_FONT_NOT_FOUND",
+            Error::ElementNotFound(_) => "ERR_ELEMENT_NOT_FOUND",
+            Error::EmptyElement(_) => "ERR_EMPTY_ELEMENT",
+            Error::InvalidCrop(_) => "ERR_INVALID_CROP",
+            Error::InvalidRegion(_) => "ERR_INVALID_REGION",
+            Error::InvalidLevels(..) => "ERR_INVALID_LEVELS",
+            #[cfg(not(target_arch = "wasm32"))]
+            Error::Napi(_) => return None,
+        })
+    }
+
+    /// The 1-based line and column of an XML parsing error.
+    pub fn position(&self) -> Option<(u32, u32)> {
+        match self {
+            Error::USvg(resvg::usvg::Error::ParsingFailed(e)) => {
+                let pos = e.pos();
+                Some((pos.row, pos.col))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Error {
+    /// Creates the javascript error, with the `code`, `line` and `column` properties set.
+    pub(crate) fn into_napi(self, env: &Env) -> napi::Error {
+        if let Error::Napi(e) = self {
+            return e;
+        }
+        let reason = format!("{self}");
+        let create = || -> napi::Result<napi::Error> {
+            // SAFETY: `env` is the live environment of the current call.
+            unsafe {
+                let error =
+                    JsError::from(napi::Error::from_reason(reason.clone())).into_value(env.raw());
+                let mut object = Object::from_napi_value(env.raw(), error)?;
+                if let Some(code) = self.code() {
+                    object.set("code", code)?;
+                }
+                if let Some((line, column)) = self.position() {
+                    object.set("line", line)?;
+                    object.set("column", column)?;
+                }
+                Ok(Unknown::from_napi_value(env.raw(), error)?.into())
+            }
+        };
+        create().unwrap_or_else(|_| napi::Error::from_reason(reason))
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 impl From<Error> for napi::Error {
     fn from(e: Error) -> Self {
-        napi::Error::from_reason(format!("{e}"))
+        match e {
+            Error::Napi(e) => e,
+            e => napi::Error::from_reason(format!("{e}")),
+        }
     }
 }
 
 #[cfg(target_arch = "wasm32")]
 impl From<Error> for js_sys::Error {
     fn from(e: Error) -> Self {
-        js_sys::Error::new(&format!("{}", e))
+        let error = js_sys::Error::new(&format!("{}", e));
+        let set = |key: &str, value: wasm_bindgen::JsValue| {
+            let _ = js_sys::Reflect::set(&error, &key.into(), &value);
+        };
+        if let Some(code) = e.code() {
+            set("code", code.into());
+        }
+        if let Some((line, column)) = e.position() {
+            set("line", line.into());
+            set("column", column.into());
+        }
+        error
     }
 }
@@ -0,0 +1,177 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use resvg::tiny_skia::{self, Mask, PathStroker, Transform};
+use resvg::usvg::{self, NodeExt, NodeKind};
+
+/// Finds the ids of the elements painted at the pixel `(x, y)` of the output, in document order,
+/// so an element always comes before its descendants.
+///
+/// `pixel_transform` maps user space to the output pixels.
+pub(crate) fn elements_at_point(
+    tree: &usvg::Tree,
+    pixel_transform: Transform,
+    x: f32,
+    y: f32,
+) -> Vec<String> {
+    // Moves the center of the pixel to the center of a 1x1 mask.
+    let transform = Transform::from_translate(-x.floor(), -y.floor()).pre_concat(pixel_transform);
+    let mut ids = vec![];
+    for child in tree.root.children() {
+        hit_node(&child, transform, Mode::Paint, &mut ids);
+    }
+    ids
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    /// The painted fill and stroke.
+    Paint,
+    /// The fill geometry of a clip path's children.
+    Clip,
+}
+
+fn hit_node(node: &usvg::Node, transform: Transform, mode: Mode, ids: &mut Vec<String>) -> bool {
+    let transform = transform.pre_concat(node.transform());
+    let index = ids.len();
+    let hit = match &*node.borrow() {
+        NodeKind::Group(g) => {
+            let clipped = g
+                .clip_path
+                .as_ref()
+                .is_some_and(|clip| !clip_contains(clip, node, transform));
+            if (mode == Mode::Paint && g.opacity.get() == 0.0) || clipped {
+                return false;
+            }
+            let mut hit = false;
+            for child in node.children() {
+                hit |= hit_node(&child, transform, mode, ids);
+            }
+            hit
+        }
+        NodeKind::Path(p) => path_contains(p, transform, mode),
+        NodeKind::Image(image) => {
+            image.visibility == usvg::Visibility::Visible
+                && covers(
+                    &tiny_skia::PathBuilder::from_rect(image.view_box.rect.to_rect()),
+                    tiny_skia::FillRule::Winding,
+                    transform,
+                )
+        }
+        // Text is converted to paths.
+        NodeKind::Text(_) => false,
+    };
+    if hit && mode == Mode::Paint && !node.id().is_empty() {
+        ids.insert(index, node.id().to_string());
+    }
+    hit
+}
+
+fn path_contains(path: &usvg::Path, transform: Transform, mode: Mode) -> bool {
+    if path.visibility != usvg::Visibility::Visible {
+        return false;
+    }
+    if mode == Mode::Clip {
+        let rule = path
+            .fill
+            .as_ref()
+            .map(|f| fill_rule(f.rule))
+            .unwrap_or(tiny_skia::FillRule::Winding);
+        return covers(&path.data, rule, transform);
+    }
+
+    let filled = path
+        .fill
+        .as_ref()
+        .filter(|fill| fill.opacity.get() > 0.0)
+        .is_some_and(|fill| covers(&path.data, fill_rule(fill.rule), transform));
+    filled
+        || path
+            .stroke
+            .as_ref()
+            .filter(|stroke| stroke.opacity.get() > 0.0)
+            .and_then(|stroke| {
+                let resolution = PathStroker::compute_resolution_scale(&transform);
+                path.data.stroke(&to_tiny_skia_stroke(stroke), resolution)
+            })
+            .is_some_and(|outline| covers(&outline, tiny_skia::FillRule::Winding, transform))
+}
+
+/// Checks if the point is inside the clip path of the group `node`.
+fn clip_contains(clip: &usvg::ClipPath, node: &usvg::Node, transform: Transform) -> bool {
+    let mut clip_transform = transform.pre_concat(clip.transform);
+    if clip.units == usvg::Units::ObjectBoundingBox {
+        match object_bbox(node) {
+            Some(bbox) => clip_transform = clip_transform.pre_concat(Transform::from_bbox(bbox)),
+            // Like resvg, clipping a zero-sized element hides it.
+            None => return false,
+        }
+    }
+    if let Some(nested) = &clip.clip_path {
+        if !clip_contains(nested, node, transform) {
+            return false;
+        }
+    }
+    clip.root
+        .children()
+        .any(|child| hit_node(&child, clip_transform, Mode::Clip, &mut vec![]))
+}
+
+/// The bounding box of the children of `node`, in its own coordinates.
+fn object_bbox(node: &usvg::Node) -> Option<usvg::NonZeroRect> {
+    let transform = node.abs_transform().invert()?;
+    let bbox = node
+        .children()
+        .filter_map(|child| child.calculate_bbox())
+        .filter_map(|bbox| bbox.transform(transform))
+        .reduce(|a, b| {
+            tiny_skia::Rect::from_ltrb(
+                a.left().min(b.left()),
+                a.top().min(b.top()),
+                a.right().max(b.right()),
+                a.bottom().max(b.bottom()),
+            )
+            .unwrap_or(a)
+        })?;
+    bbox.to_non_zero_rect()
+}
+
+/// Checks if the path covers the center of the pixel at the origin.
+fn covers(path: &tiny_skia::Path, rule: tiny_skia::FillRule, transform: Transform) -> bool {
+    let Some(mut mask) = Mask::new(1, 1) else {
+        return false;
+    };
+    mask.fill_path(path, rule, false, transform);
+    mask.data()[0] > 0
+}
+
+fn fill_rule(rule: usvg::FillRule) -> tiny_skia::FillRule {
+    match rule {
+        usvg::FillRule::NonZero => tiny_skia::FillRule::Winding,
+        usvg::FillRule::EvenOdd => tiny_skia::FillRule::EvenOdd,
+    }
+}
+
+/// Converts the stroke the same way resvg does, including the dashes.
+pub(crate) fn to_tiny_skia_stroke(stroke: &usvg::Stroke) -> tiny_skia::Stroke {
+    tiny_skia::Stroke {
+        width: stroke.width.get(),
+        miter_limit: stroke.miterlimit.get(),
+        line_cap: match stroke.linecap {
+            usvg::LineCap::Butt => tiny_skia::LineCap::Butt,
+            usvg::LineCap::Round => tiny_skia::LineCap::Round,
+            usvg::LineCap::Square => tiny_skia::LineCap::Square,
+        },
+        line_join: match stroke.linejoin {
+            usvg::LineJoin::Miter => tiny_skia::LineJoin::Miter,
+            usvg::LineJoin::MiterClip => tiny_skia::LineJoin::MiterClip,
+            usvg::LineJoin::Round => tiny_skia::LineJoin::Round,
+            usvg::LineJoin::Bevel => tiny_skia::LineJoin::Bevel,
+        },
+        dash: stroke
+            .dasharray
+            .as_ref()
+            .and_then(|dashes| tiny_skia::StrokeDash::new(dashes.clone(), stroke.dashoffset)),
+    }
+}
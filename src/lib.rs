@@ -2,6 +2,11 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::borrow::Cow;
+use std::cell::{OnceCell, RefCell};
+use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
 use std::sync::Arc;
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -9,21 +14,22 @@ use std::{any::Any, panic::AssertUnwindSafe};
 
 #[cfg(not(target_arch = "wasm32"))]
 use napi::bindgen_prelude::{
-    AbortSignal, AsyncTask, Buffer, Either, Env, Error as NapiError, ObjectFinalize, Task,
-    Undefined,
+    AbortSignal, AsyncTask, Buffer, Either, Env, Error as NapiError, Function, ObjectFinalize,
+    Promise, Task, Undefined,
 };
 #[cfg(not(target_arch = "wasm32"))]
+use napi::{Status, threadsafe_function::ThreadsafeFunction};
+#[cfg(not(target_arch = "wasm32"))]
 use napi_derive::napi;
-use options::JsOptions;
 #[cfg(not(target_arch = "wasm32"))]
 use options::ResvgReadable;
+use options::{BBoxKind, JsOptions, ResolvedImages};
 use pathfinder_content::{
     outline::{Contour, Outline},
     stroke::{LineCap, LineJoin, OutlineStrokeToFill, StrokeStyle},
 };
 use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::vector::Vector2F;
-#[cfg(target_arch = "wasm32")]
 use resvg::usvg::TreeParsing;
 use resvg::{
     tiny_skia::{PathSegment, Pixmap, Point},
@@ -35,9 +41,17 @@ use wasm_bindgen::{
     prelude::{JsValue, wasm_bindgen},
 };
 
+mod element;
 mod error;
 mod fonts;
+mod hit;
+mod limits;
+mod logger;
 mod options;
+mod sprite;
+mod text;
+mod tiles;
+mod trim;
 
 use error::Error;
 use usvg::NodeExt;
@@ -56,7 +70,7 @@ unsafe extern "C" {
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[cfg_attr(not(target_arch = "wasm32"), napi)]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct BBox {
     pub x: f64,
     pub y: f64,
@@ -69,8 +83,26 @@ pub struct BBox {
 pub struct Resvg {
     tree: usvg::Tree,
     js_options: JsOptions,
+    // Text nodes captured before `convert_text()` replaces them with paths.
+    texts: Vec<usvg::Text>,
+    // Kept to shape the captured text nodes and the text of resolved SVG images on demand.
+    fontdb: Arc<usvg::fontdb::Database>,
+    // The source of a document with `<symbol>` elements, which are not part of the rendered
+    // tree, parsed again on demand by `renderSymbols()` and `renderElement()`.
+    // It is dropped once the symbols are parsed.
+    symbol_source: RefCell<Option<Vec<u8>>>,
+    symbols: OnceCell<Vec<sprite::Symbol>>,
+    // The images returned by the `imageResolver`, reused when the source is parsed again.
+    resolved_images: Arc<ResolvedImages>,
     // Indicates the crop area has no visible content; render can skip resvg to avoid panics.
     cropped_empty: bool,
+    // The geometry before `cropByBBox()`, restored by `resetCrop()`.
+    original_view_box: usvg::ViewBox,
+    original_size: usvg::Size,
+    // The group moving the content of a `normalize` crop, and its offset.
+    normalized: Option<(usvg::Node, f32, f32)>,
+    // Warnings logged while parsing, rendering and resolving images.
+    warnings: RefCell<Vec<logger::Warning>>,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
@@ -81,6 +113,89 @@ pub struct RenderedImage {
     accounted_bytes: i64,
 }
 
+/// A symbol rendered by `renderSymbols()`.
+#[cfg(not(target_arch = "wasm32"))]
+#[napi(object, object_from_js = false)]
+pub struct SymbolImage {
+    pub id: String,
+    /// A `RenderedImage`, or a PNG buffer with `format: 'png'`.
+    pub image: Either<RenderedImage, Buffer>,
+}
+
+/// The sprite sheet built by `packSpriteSheet()`.
+#[cfg(not(target_arch = "wasm32"))]
+#[napi(object, object_from_js = false)]
+pub struct SpriteSheet {
+    pub image: RenderedImage,
+    /// The position of every SVG, in the order they were passed.
+    pub frames: Vec<sprite::SpriteFrame>,
+}
+
+/// A tile exported by `exportTilePyramid()`.
+#[cfg(not(target_arch = "wasm32"))]
+#[napi(object, object_from_js = false)]
+pub struct PyramidTile {
+    pub level: u32,
+    pub column: u32,
+    pub row: u32,
+    /// The position of the tile in its level, including the overlap.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// A PNG buffer, or a `RenderedImage` with `format: 'rgba'`.
+    pub image: Either<RenderedImage, Buffer>,
+}
+
+/// The Deep Zoom pyramid built by `exportTilePyramid()`.
+#[cfg(not(target_arch = "wasm32"))]
+#[napi(object, object_from_js = false)]
+pub struct TilePyramid {
+    /// The `.dzi` descriptor, only set for PNG tiles.
+    pub dzi: Option<String>,
+    /// The size of the full size image, at the last level of the pyramid.
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: u32,
+    pub overlap: u32,
+    pub min_level: u32,
+    pub max_level: u32,
+    pub tiles: Vec<PyramidTile>,
+}
+
+/// A tile rendered by `renderTiles()`.
+#[cfg(not(target_arch = "wasm32"))]
+#[napi(object, object_from_js = false)]
+pub struct Tile {
+    /// The position of the tile in the whole image.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// A `RenderedImage`, or a PNG buffer with `format: 'png'`.
+    pub image: Either<RenderedImage, Buffer>,
+}
+
+impl From<RectF> for BBox {
+    fn from(rect: RectF) -> BBox {
+        BBox {
+            x: rect.origin_x() as f64,
+            y: rect.origin_y() as f64,
+            width: rect.width() as f64,
+            height: rect.height() as f64,
+        }
+    }
+}
+
+/// The bounding box of an element returned by `elementBBoxes()`.
+#[cfg_attr(not(target_arch = "wasm32"), napi(object, object_from_js = false))]
+pub struct ElementBBox {
+    /// In user space, like `getElementBBox()`.
+    pub user: BBox,
+    /// In the pixels of `render()`, after `fitTo` and the `crop` option.
+    pub pixel: BBox,
+}
+
 struct CropResult {
     view_x: f32,
     view_y: f32,
@@ -96,7 +211,8 @@ struct CropContext {
     y: f32,
     width: f32,
     height: f32,
-    padding: f32,
+    // [top, right, bottom, left]
+    padding: [f32; 4],
 }
 
 impl CropContext {
@@ -107,8 +223,9 @@ impl CropContext {
         size_width: f32,
         size_height: f32,
     ) -> CropResult {
-        let content_target_width = (target_width - self.padding * 2.0).max(0.0);
-        let content_target_height = (target_height - self.padding * 2.0).max(0.0);
+        let [top, right, bottom, left] = self.padding;
+        let content_target_width = (target_width - left - right).max(0.0);
+        let content_target_height = (target_height - top - bottom).max(0.0);
 
         // Using 1.0 as threshold to prevent division by near-zero values that could cause OOM
         if content_target_width < 1.0 || content_target_height < 1.0 {
@@ -123,12 +240,12 @@ impl CropContext {
             };
         }
 
-        let viewbox_width = self.width * target_width / content_target_width;
-        let viewbox_height = self.height * target_height / content_target_height;
-        let bbox_center_x = self.x + self.width / 2.0;
-        let bbox_center_y = self.y + self.height / 2.0;
-        let viewbox_x = bbox_center_x - viewbox_width / 2.0;
-        let viewbox_y = bbox_center_y - viewbox_height / 2.0;
+        let scale_x = self.width / content_target_width;
+        let scale_y = self.height / content_target_height;
+        let viewbox_width = target_width * scale_x;
+        let viewbox_height = target_height * scale_y;
+        let viewbox_x = self.x - left * scale_x;
+        let viewbox_y = self.y - top * scale_y;
 
         CropResult {
             view_x: viewbox_x,
@@ -184,8 +301,11 @@ impl RenderedImage {
     #[cfg(not(target_arch = "wasm32"))]
     #[napi]
     /// Write the image data to Buffer
-    pub fn as_png(&self) -> Result<Buffer, NapiError> {
-        let buffer = self.pix.encode_png().map_err(Error::from)?;
+    pub fn as_png(&self, env: Env) -> Result<Buffer, NapiError> {
+        let buffer = self
+            .pix
+            .encode_png()
+            .map_err(|e| Error::from(e).into_napi(&env))?;
         Ok(buffer.into())
     }
 
@@ -239,49 +359,321 @@ impl RenderedImage {
     pub fn height(&self) -> u32 {
         self.pix.height()
     }
+
+    #[cfg_attr(not(target_arch = "wasm32"), napi)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = opaqueBounds))]
+    /// Get the smallest rectangle containing every pixel whose alpha is above `threshold` (default: 0)
+    pub fn opaque_bounds(&self, threshold: Option<u8>) -> Option<BBox> {
+        let rect = trim::opaque_bounds(&self.pix, threshold.unwrap_or(0))?;
+        Some(BBox {
+            x: rect.x() as f64,
+            y: rect.y() as f64,
+            width: rect.width() as f64,
+            height: rect.height() as f64,
+        })
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 #[napi]
 impl Resvg {
     #[napi(constructor)]
-    pub fn new(svg: Either<String, Buffer>, options: Option<String>) -> Result<Resvg, NapiError> {
-        Resvg::new_inner(&svg, options)
+    pub fn new(
+        env: Env,
+        svg: Either<String, Buffer>,
+        options: Option<String>,
+        image_resolver: Option<Function<String, Option<Buffer>>>,
+    ) -> Result<Resvg, NapiError> {
+        Resvg::new_with_resolver(&svg, options, image_resolver).map_err(|e| e.into_napi(&env))
     }
 
-    fn new_inner(
-        svg: &Either<String, Buffer>,
+    #[napi(factory)]
+    /// Load an SVG or SVGZ file, relative image hrefs are resolved against its directory by default
+    pub fn from_file(
+        env: Env,
+        path: String,
         options: Option<String>,
+        image_resolver: Option<Function<String, Option<Buffer>>>,
     ) -> Result<Resvg, NapiError> {
-        let js_options: JsOptions = options
-            .and_then(|o| serde_json::from_str(o.as_str()).ok())
-            .unwrap_or_default();
-        let _ = env_logger::builder()
-            .filter_level(js_options.log_level)
-            .try_init();
+        Resvg::new_with_resolver(&PathBuf::from(path), options, image_resolver)
+            .map_err(|e| e.into_napi(&env))
+    }
+
+    fn new_with_resolver(
+        svg: &dyn ResvgReadable,
+        options: Option<String>,
+        image_resolver: Option<Function<String, Option<Buffer>>>,
+    ) -> Result<Resvg, Error> {
+        match image_resolver {
+            Some(resolver) => {
+                let resolve_images = |hrefs: Vec<String>| -> Result<ResolvedImages, Error> {
+                    let mut resolved = ResolvedImages::new();
+                    for href in hrefs {
+                        if let Some(buffer) = resolver.call(href.clone())? {
+                            options::add_resolved_image(&mut resolved, href, buffer.to_vec())?;
+                        }
+                    }
+                    Ok(resolved)
+                };
+                Resvg::new_inner(svg, options, Some(&resolve_images))
+            }
+            None => Resvg::new_inner(svg, options, None),
+        }
+    }
+
+    fn new_inner(
+        svg: &dyn ResvgReadable,
+        options: Option<String>,
+        resolve_images: Option<&dyn Fn(Vec<String>) -> Result<ResolvedImages, Error>>,
+    ) -> Result<Resvg, Error> {
+        let mut js_options: JsOptions = options::parse_options(options)?;
+        let collector = logger::WarningCollector::start(js_options.log_level);
+        if js_options.resources_dir.is_none() {
+            js_options.resources_dir = svg
+                .resources_dir()
+                .map(|dir| dir.to_string_lossy().into_owned());
+        }
+
+        let deadline = js_options.limits.render_timeout.map(limits::Deadline::new);
+        let svg_data = svg.read(&js_options.limits)?;
+        let hrefs =
+            limits::check_document(&svg_data, &js_options.limits, resolve_images.is_some())?;
 
         let (mut opts, fontdb) = js_options.to_usvg_options();
-        options::tweak_usvg_options(&mut opts);
+        let fontdb = Arc::new(fontdb);
+        let resolved = match resolve_images {
+            Some(resolve_images) if !hrefs.is_empty() => resolve_images(hrefs)?,
+            _ => ResolvedImages::new(),
+        };
+        Self::check_deadline(deadline.as_ref())?;
+        let resolved_images = Arc::new(resolved);
+        let guard = options::tweak_usvg_options(
+            &mut opts,
+            js_options.limits,
+            js_options.resources_sandbox,
+            resolved_images.clone(),
+            fontdb.clone(),
+            deadline,
+        );
         // Parse the SVG string into a tree.
-        let mut tree = svg
-            .load(&opts)
-            .map_err(|e| napi::Error::from_reason(format!("{e}")))?;
+        let mut tree = usvg::Tree::from_data(&svg_data, &opts)?;
+        guard.finish()?;
+        Self::check_parsed(&tree, &js_options, deadline.as_ref())?;
+        let symbol_source = sprite::has_symbols(&svg_data).then(|| svg_data.into_owned());
+        let texts = text::capture_text_nodes(&tree);
         tree.convert_text(&fontdb);
-        Ok(Resvg {
+        Self::check_deadline(deadline.as_ref())?;
+        let resvg = Resvg {
+            original_view_box: tree.view_box,
+            original_size: tree.size,
+            normalized: None,
             tree,
             js_options,
+            texts,
+            fontdb,
+            symbol_source: RefCell::new(symbol_source),
+            symbols: OnceCell::new(),
+            resolved_images,
             cropped_empty: false,
-        })
+            warnings: RefCell::default(),
+        };
+        resvg.add_warnings(collector.finish());
+        Ok(resvg)
     }
 
     #[napi]
     /// Renders an SVG in Node.js
     pub fn render(&self, mut env: Env) -> Result<RenderedImage, NapiError> {
-        let mut rendered = self.render_inner_catch_unwind()?;
+        let mut rendered = self
+            .render_inner_catch_unwind()
+            .map_err(|e| e.into_napi(&env))?;
+        rendered.account_external_memory(&mut env)?;
+        Ok(rendered)
+    }
+
+    #[napi]
+    /// Renders only the element with `id` and its subtree, cropped to its bounding box
+    pub fn render_element(
+        &self,
+        mut env: Env,
+        id: String,
+        options: Option<String>,
+    ) -> Result<RenderedImage, NapiError> {
+        let mut rendered =
+            std::panic::catch_unwind(AssertUnwindSafe(|| self.render_element_inner(&id, options)))
+                .unwrap_or_else(|panic| Err(Error::RenderPanic(panic_to_string(panic))))
+                .map_err(|e| e.into_napi(&env))?;
+        rendered.account_external_memory(&mut env)?;
+        Ok(rendered)
+    }
+
+    #[napi]
+    /// Renders every `<symbol>` and top-level group with an id, e.g. the icons of a sprite
+    pub fn render_symbols(
+        &self,
+        mut env: Env,
+        options: Option<String>,
+    ) -> Result<Vec<SymbolImage>, NapiError> {
+        let render = || {
+            let options: sprite::JsRenderSymbolsOptions = options::parse_options(options)?;
+            let images =
+                std::panic::catch_unwind(AssertUnwindSafe(|| self.render_symbols_inner(&options)))
+                    .unwrap_or_else(|panic| Err(Error::RenderPanic(panic_to_string(panic))))?;
+            Ok::<_, Error>((options.format, images))
+        };
+        let (format, images) = render().map_err(|e| e.into_napi(&env))?;
+        images
+            .into_iter()
+            .map(|(id, mut image)| {
+                let image = match format {
+                    options::ImageFormat::Rgba => {
+                        image.account_external_memory(&mut env)?;
+                        Either::A(image)
+                    }
+                    options::ImageFormat::Png => Either::B(image.as_png(env)?),
+                };
+                Ok::<_, NapiError>(SymbolImage { id, image })
+            })
+            .collect()
+    }
+
+    #[napi]
+    /// Renders the `region` of user space at a given scale, without changing the viewbox
+    pub fn render_region(
+        &self,
+        mut env: Env,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        options: Option<String>,
+    ) -> Result<RenderedImage, NapiError> {
+        let region = BBox {
+            x,
+            y,
+            width,
+            height,
+        };
+        let mut rendered = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            self.render_region_inner(&region, options)
+        }))
+        .unwrap_or_else(|panic| Err(Error::RenderPanic(panic_to_string(panic))))
+        .map_err(|e| e.into_napi(&env))?;
         rendered.account_external_memory(&mut env)?;
         Ok(rendered)
     }
 
+    #[napi]
+    /// Renders the image tile by tile and passes every tile to `callback`, row by row
+    pub fn render_tiles(
+        &self,
+        mut env: Env,
+        options: Option<String>,
+        callback: Function<Tile, ()>,
+    ) -> Result<(), NapiError> {
+        let render = || {
+            let options: tiles::JsRenderTilesOptions = options::parse_options(options)?;
+            let emit = |rect: resvg::tiny_skia::IntRect, pix: Pixmap| {
+                let mut image = RenderedImage {
+                    pix,
+                    accounted_bytes: 0,
+                };
+                let image = match options.format {
+                    options::ImageFormat::Rgba => {
+                        image.account_external_memory(&mut env)?;
+                        Either::A(image)
+                    }
+                    options::ImageFormat::Png => Either::B(image.as_png(env)?),
+                };
+                callback.call(Tile {
+                    x: rect.x() as u32,
+                    y: rect.y() as u32,
+                    width: rect.width(),
+                    height: rect.height(),
+                    image,
+                })?;
+                Ok::<_, Error>(())
+            };
+            std::panic::catch_unwind(AssertUnwindSafe(|| self.render_tiles_inner(&options, emit)))
+                .unwrap_or_else(|panic| Err(Error::RenderPanic(panic_to_string(panic))))
+        };
+        render().map_err(|e| e.into_napi(&env))
+    }
+
+    #[napi]
+    /// Renders every level of a Deep Zoom pyramid from the vector tree, e.g. for OpenSeadragon
+    pub fn export_tile_pyramid(
+        &self,
+        mut env: Env,
+        options: Option<String>,
+    ) -> Result<TilePyramid, NapiError> {
+        let mut exported = vec![];
+        let render = || {
+            let options: tiles::JsTilePyramidOptions = options::parse_options(options)?;
+            let emit = |tile: tiles::PyramidTile, pix: Pixmap| {
+                let mut image = RenderedImage {
+                    pix,
+                    accounted_bytes: 0,
+                };
+                let image = match options.format {
+                    options::ImageFormat::Rgba => {
+                        image.account_external_memory(&mut env)?;
+                        Either::A(image)
+                    }
+                    options::ImageFormat::Png => Either::B(image.as_png(env)?),
+                };
+                exported.push(PyramidTile {
+                    level: tile.level,
+                    column: tile.column,
+                    row: tile.row,
+                    x: tile.rect.x() as u32,
+                    y: tile.rect.y() as u32,
+                    width: tile.rect.width(),
+                    height: tile.rect.height(),
+                    image,
+                });
+                Ok::<_, Error>(())
+            };
+            let (pyramid, min_level, max_level) =
+                std::panic::catch_unwind(AssertUnwindSafe(|| {
+                    self.export_tile_pyramid_inner(&options, emit)
+                }))
+                .unwrap_or_else(|panic| Err(Error::RenderPanic(panic_to_string(panic))))?;
+            Ok::<_, Error>(TilePyramid {
+                dzi: pyramid.dzi(&options),
+                width: pyramid.width,
+                height: pyramid.height,
+                tile_size: options.tile_size.get(),
+                overlap: options.overlap,
+                min_level,
+                max_level,
+                tiles: vec![],
+            })
+        };
+        let mut pyramid = render().map_err(|e| e.into_napi(&env))?;
+        pyramid.tiles = exported;
+        Ok(pyramid)
+    }
+
+    #[napi]
+    /// Encodes the image as a PNG strip by strip, without holding the whole image in memory
+    pub fn render_png_stream(
+        &self,
+        env: Env,
+        options: Option<String>,
+        callback: Function<Buffer, ()>,
+    ) -> Result<(), NapiError> {
+        let render = || {
+            let options: tiles::JsPngStreamOptions = options::parse_options(options)?;
+            let emit = |chunk: Vec<u8>| Ok::<_, Error>(callback.call(chunk.into())?);
+            std::panic::catch_unwind(AssertUnwindSafe(|| {
+                self.render_png_stream_inner(&options, emit)
+            }))
+            .unwrap_or_else(|panic| Err(Error::RenderPanic(panic_to_string(panic))))
+        };
+        render().map_err(|e| e.into_napi(&env))
+    }
+
     #[napi]
     /// Output usvg-simplified SVG string
     #[allow(clippy::inherent_to_string)]
@@ -295,33 +687,16 @@ impl Resvg {
     #[napi(js_name = innerBBox)]
     /// Calculate a maximum bounding box of all visible elements in this SVG.
     ///
-    /// Note: path bounding box are approx values.
-    pub fn inner_bbox(&self) -> Either<BBox, Undefined> {
-        let rect = self.tree.view_box.rect;
-        let rect = points_to_rect(
-            Vector2F::new(rect.x(), rect.y()),
-            Vector2F::new(rect.right(), rect.bottom()),
-        );
-        let mut v = None;
-        for child in self.tree.root.children() {
-            let child_viewbox = match self.node_bbox(child).and_then(|v| v.intersection(rect)) {
-                Some(v) => v,
-                None => continue,
-            };
-            if let Some(v) = v.as_mut() {
-                *v = child_viewbox.union_rect(*v);
-            } else {
-                v = Some(child_viewbox)
-            };
-        }
-        match v {
-            Some(v) => Either::A(BBox {
-                x: v.min_x().floor() as f64,
-                y: v.min_y().floor() as f64,
-                width: (v.max_x().ceil() - v.min_x().floor()) as f64,
-                height: (v.max_y().ceil() - v.min_y().floor()) as f64,
-            }),
-            None => Either::B(()),
+    /// Note: path bounding box are approx values, unless `kind` is `ink`.
+    pub fn inner_bbox(
+        &self,
+        env: Env,
+        options: Option<String>,
+    ) -> Result<Either<BBox, Undefined>, NapiError> {
+        match self.inner_bbox_inner(options) {
+            Ok(Some(bbox)) => Ok(Either::A(bbox)),
+            Ok(None) => Ok(Either::B(())),
+            Err(e) => Err(e.into_napi(&env)),
         }
     }
 
@@ -343,27 +718,119 @@ impl Resvg {
         }
     }
 
+    #[napi]
+    /// Calculate the bounding box of the element with `id` in user space.
+    pub fn get_element_bbox(
+        &self,
+        env: Env,
+        id: String,
+        options: Option<String>,
+    ) -> Result<Either<BBox, Undefined>, NapiError> {
+        match self.get_element_bbox_inner(&id, options) {
+            Ok(Some(bbox)) => Ok(Either::A(bbox)),
+            Ok(None) => Ok(Either::B(())),
+            Err(e) => Err(e.into_napi(&env)),
+        }
+    }
+
+    #[napi(js_name = elementBBoxes)]
+    /// Calculate the bounding box of every element with an id, in user space and in output pixels.
+    pub fn element_bboxes(
+        &self,
+        env: Env,
+        options: Option<String>,
+    ) -> Result<BTreeMap<String, ElementBBox>, NapiError> {
+        self.element_bboxes_inner(options)
+            .map_err(|e| e.into_napi(&env))
+    }
+
+    #[napi]
+    /// Get the ids of the elements painted at the pixel `(x, y)` of the rendered image,
+    /// from the outermost to the innermost.
+    pub fn elements_at_point(&self, env: Env, x: f64, y: f64) -> Result<Vec<String>, NapiError> {
+        self.elements_at_point_inner(x, y)
+            .map_err(|e| e.into_napi(&env))
+    }
+
     #[napi(js_name = cropByBBox)]
-    /// Use a given `BBox` to crop the svg. Currently this method simply changes
-    /// the viewbox/size of the svg and do not move the elements for simplicity
+    /// Use a given `BBox` to crop the svg. By default this method simply changes
+    /// the viewbox/size of the svg, `{ normalize: true }` also moves the elements to 0,0
     ///
     /// # Arguments
     /// * `bbox` - The bounding box to crop to
     /// * `padding` - Optional bleed area around the crop box (default: 0.0)
     /// * `square` - Optional flag to make the crop area square using the larger dimension (default: false)
-    pub fn crop_by_bbox(&mut self, bbox: &BBox, padding: Option<f64>, square: Option<bool>) {
-        self.crop_by_bbox_inner(bbox, padding, square);
+    /// * `relative` - Optional flag to position `bbox` relative to the current crop instead of the user space (default: false)
+    pub fn crop_by_bbox(
+        &mut self,
+        bbox: &BBox,
+        padding: Option<f64>,
+        square: Option<bool>,
+        relative: Option<bool>,
+    ) {
+        self.crop_by_bbox_inner(
+            bbox,
+            &options::JsCropByBBoxOptions {
+                padding: options::JsCropPadding::Uniform(padding.unwrap_or(0.0)),
+                square: square.unwrap_or(false),
+                relative: relative.unwrap_or(false),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[napi(js_name = cropByBBoxWithOptions)]
+    /// Use a given `BBox` to crop the svg, with per side padding, an aspect ratio,
+    /// or moving the content to 0,0
+    pub fn crop_by_bbox_with_options(
+        &mut self,
+        env: Env,
+        bbox: &BBox,
+        options: Option<String>,
+    ) -> Result<(), NapiError> {
+        let options = options::parse_options(options).map_err(|e| e.into_napi(&env))?;
+        self.crop_by_bbox_inner(bbox, &options);
+        Ok(())
+    }
+
+    #[napi]
+    /// Restore the viewbox and size of the SVG before `cropByBBox()`
+    pub fn reset_crop(&mut self) {
+        self.reset_crop_inner();
+    }
+
+    #[napi]
+    /// Get the area cropped by `cropByBBox()` in user space, including the padding
+    pub fn get_crop(&self) -> Either<BBox, Undefined> {
+        match self.get_crop_inner() {
+            Some(bbox) => Either::A(bbox),
+            None => Either::B(()),
+        }
+    }
+
+    #[napi]
+    /// Get all text chunks with their font, fill and bounding box in user space.
+    pub fn text_content(&self) -> Vec<text::TextChunk> {
+        text::text_chunks(&self.texts, &self.tree, &self.fontdb)
     }
 
     #[napi]
-    pub fn images_to_resolve(&self) -> Result<Vec<String>, NapiError> {
-        Ok(self.images_to_resolve_inner()?)
+    /// Get the warnings logged while parsing, rendering and resolving images.
+    pub fn warnings(&self) -> Vec<logger::Warning> {
+        self.warnings.borrow().clone()
     }
 
     #[napi]
-    pub fn resolve_image(&self, href: String, buffer: Buffer) -> Result<(), NapiError> {
+    pub fn images_to_resolve(&self, env: Env) -> Result<Vec<String>, NapiError> {
+        self.images_to_resolve_inner()
+            .map_err(|e| e.into_napi(&env))
+    }
+
+    #[napi]
+    pub fn resolve_image(&self, env: Env, href: String, buffer: Buffer) -> Result<(), NapiError> {
         let buffer = buffer.to_vec();
-        Ok(self.resolve_image_inner(href, buffer)?)
+        self.resolve_image_inner(href, buffer)
+            .map_err(|e| e.into_napi(&env))
     }
 
     /// Get the SVG width
@@ -387,31 +854,72 @@ impl Resvg {
         svg: IStringOrBuffer,
         options: Option<String>,
         custom_font_buffers: Option<js_sys::Array>,
+        image_resolver: Option<js_sys::Function>,
     ) -> Result<Resvg, js_sys::Error> {
-        let js_options: JsOptions = options
-            .and_then(|o| serde_json::from_str(o.as_str()).ok())
-            .unwrap_or_default();
+        let js_options: JsOptions = options::parse_options(options)?;
+        let collector = logger::WarningCollector::start(js_options.log_level);
 
         let (mut opts, mut fontdb) = js_options.to_usvg_options();
 
         crate::fonts::load_wasm_fonts(&js_options.font, custom_font_buffers, &mut fontdb)?;
+        let fontdb = Arc::new(fontdb);
 
-        options::tweak_usvg_options(&mut opts);
-        let mut tree = if js_sys::Uint8Array::instanceof(&svg) {
-            let uintarray = js_sys::Uint8Array::unchecked_from_js_ref(&svg);
-            let svg_buffer = uintarray.to_vec();
-            usvg::Tree::from_data(&svg_buffer, &opts).map_err(Error::from)
+        let deadline = js_options.limits.render_timeout.map(limits::Deadline::new);
+        let svg_data = if js_sys::Uint8Array::instanceof(&svg) {
+            js_sys::Uint8Array::unchecked_from_js_ref(&svg).to_vec()
         } else if let Some(s) = svg.as_string() {
-            usvg::Tree::from_str(s.as_str(), &opts).map_err(Error::from)
+            s.into_bytes()
         } else {
-            Err(Error::InvalidInput)
-        }?;
+            return Err(Error::InvalidInput.into());
+        };
+        let svg_data = limits::decompress_svgz(&svg_data, &js_options.limits)?;
+        let hrefs =
+            limits::check_document(&svg_data, &js_options.limits, image_resolver.is_some())?;
+
+        let mut resolved = ResolvedImages::new();
+        if let Some(resolver) = image_resolver {
+            for href in hrefs {
+                let buffer = resolver.call1(&JsValue::NULL, &JsValue::from_str(&href))?;
+                if js_sys::Uint8Array::instanceof(&buffer) {
+                    let buffer = js_sys::Uint8Array::unchecked_from_js(buffer).to_vec();
+                    options::add_resolved_image(&mut resolved, href, buffer)?;
+                }
+            }
+        }
+
+        Resvg::check_deadline(deadline.as_ref())?;
+        let resolved_images = Arc::new(resolved);
+        let guard = options::tweak_usvg_options(
+            &mut opts,
+            js_options.limits,
+            js_options.resources_sandbox,
+            resolved_images.clone(),
+            fontdb.clone(),
+            deadline,
+        );
+        let mut tree = usvg::Tree::from_data(&svg_data, &opts).map_err(Error::from)?;
+        guard.finish()?;
+        Resvg::check_parsed(&tree, &js_options, deadline.as_ref())?;
+        let symbol_source = sprite::has_symbols(&svg_data).then(|| svg_data.into_owned());
+        let texts = text::capture_text_nodes(&tree);
         tree.convert_text(&fontdb);
-        Ok(Resvg {
+        Resvg::check_deadline(deadline.as_ref())?;
+        let resvg = Resvg {
+            original_view_box: tree.view_box,
+            original_size: tree.size,
+            normalized: None,
             tree,
             js_options,
+            texts,
+            fontdb,
+            symbol_source: RefCell::new(symbol_source),
+            symbols: OnceCell::new(),
+            resolved_images,
             cropped_empty: false,
-        })
+            warnings: RefCell::default(),
+        };
+        resvg.add_warnings(collector.finish());
+        Ok(resvg)
     }
 
     /// Get the SVG width
@@ -431,6 +939,135 @@ impl Resvg {
         Ok(self.render_inner()?)
     }
 
+    /// Renders only the element with `id` and its subtree, cropped to its bounding box
+    #[wasm_bindgen(js_name = renderElement)]
+    pub fn render_element(
+        &self,
+        id: String,
+        options: Option<String>,
+    ) -> Result<RenderedImage, js_sys::Error> {
+        Ok(self.render_element_inner(&id, options)?)
+    }
+
+    /// Renders every `<symbol>` and top-level group with an id, e.g. the icons of a sprite
+    #[wasm_bindgen(js_name = renderSymbols)]
+    pub fn render_symbols(&self, options: Option<String>) -> Result<js_sys::Array, js_sys::Error> {
+        let options: sprite::JsRenderSymbolsOptions = options::parse_options(options)?;
+        let images = self.render_symbols_inner(&options)?;
+        let result = js_sys::Array::new();
+        for (id, image) in images {
+            let image = match options.format {
+                options::ImageFormat::Rgba => JsValue::from(image),
+                options::ImageFormat::Png => image.as_png()?.into(),
+            };
+            let object = js_sys::Object::new();
+            js_sys::Reflect::set(&object, &"id".into(), &id.into())?;
+            js_sys::Reflect::set(&object, &"image".into(), &image)?;
+            result.push(&object);
+        }
+        Ok(result)
+    }
+
+    /// Renders the `region` of user space at a given scale, without changing the viewbox
+    #[wasm_bindgen(js_name = renderRegion)]
+    pub fn render_region(
+        &self,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        options: Option<String>,
+    ) -> Result<RenderedImage, js_sys::Error> {
+        let region = BBox {
+            x,
+            y,
+            width,
+            height,
+        };
+        Ok(self.render_region_inner(&region, options)?)
+    }
+
+    /// Renders the image tile by tile and passes every tile to `callback`, row by row
+    #[wasm_bindgen(js_name = renderTiles)]
+    pub fn render_tiles(
+        &self,
+        options: Option<String>,
+        callback: js_sys::Function,
+    ) -> Result<(), js_sys::Error> {
+        let options: tiles::JsRenderTilesOptions = options::parse_options(options)?;
+        self.render_tiles_inner(&options, |rect, pix| {
+            let image = RenderedImage { pix };
+            let image = match options.format {
+                options::ImageFormat::Rgba => JsValue::from(image),
+                options::ImageFormat::Png => image.as_png()?.into(),
+            };
+            let object = js_sys::Object::new();
+            js_sys::Reflect::set(&object, &"x".into(), &rect.x().into())?;
+            js_sys::Reflect::set(&object, &"y".into(), &rect.y().into())?;
+            js_sys::Reflect::set(&object, &"width".into(), &rect.width().into())?;
+            js_sys::Reflect::set(&object, &"height".into(), &rect.height().into())?;
+            js_sys::Reflect::set(&object, &"image".into(), &image)?;
+            callback.call1(&JsValue::NULL, &object)?;
+            Ok(())
+        })
+    }
+
+    /// Renders every level of a Deep Zoom pyramid from the vector tree, e.g. for OpenSeadragon
+    #[wasm_bindgen(js_name = exportTilePyramid)]
+    pub fn export_tile_pyramid(
+        &self,
+        options: Option<String>,
+    ) -> Result<js_sys::Object, js_sys::Error> {
+        let options: tiles::JsTilePyramidOptions = options::parse_options(options)?;
+        let exported = js_sys::Array::new();
+        let (pyramid, min_level, max_level) =
+            self.export_tile_pyramid_inner(&options, |tile, pix| {
+                let image = RenderedImage { pix };
+                let image = match options.format {
+                    options::ImageFormat::Rgba => JsValue::from(image),
+                    options::ImageFormat::Png => image.as_png()?.into(),
+                };
+                let object = js_sys::Object::new();
+                js_sys::Reflect::set(&object, &"level".into(), &tile.level.into())?;
+                js_sys::Reflect::set(&object, &"column".into(), &tile.column.into())?;
+                js_sys::Reflect::set(&object, &"row".into(), &tile.row.into())?;
+                js_sys::Reflect::set(&object, &"x".into(), &tile.rect.x().into())?;
+                js_sys::Reflect::set(&object, &"y".into(), &tile.rect.y().into())?;
+                js_sys::Reflect::set(&object, &"width".into(), &tile.rect.width().into())?;
+                js_sys::Reflect::set(&object, &"height".into(), &tile.rect.height().into())?;
+                js_sys::Reflect::set(&object, &"image".into(), &image)?;
+                exported.push(&object);
+                Ok::<_, js_sys::Error>(())
+            })?;
+        let result = js_sys::Object::new();
+        if let Some(dzi) = pyramid.dzi(&options) {
+            js_sys::Reflect::set(&result, &"dzi".into(), &dzi.into())?;
+        }
+        js_sys::Reflect::set(&result, &"width".into(), &pyramid.width.into())?;
+        js_sys::Reflect::set(&result, &"height".into(), &pyramid.height.into())?;
+        js_sys::Reflect::set(&result, &"tileSize".into(), &options.tile_size.get().into())?;
+        js_sys::Reflect::set(&result, &"overlap".into(), &options.overlap.into())?;
+        js_sys::Reflect::set(&result, &"minLevel".into(), &min_level.into())?;
+        js_sys::Reflect::set(&result, &"maxLevel".into(), &max_level.into())?;
+        js_sys::Reflect::set(&result, &"tiles".into(), &exported)?;
+        Ok(result)
+    }
+
+    /// Encodes the image as a PNG strip by strip, without holding the whole image in memory
+    #[wasm_bindgen(js_name = renderPngStream)]
+    pub fn render_png_stream(
+        &self,
+        options: Option<String>,
+        callback: js_sys::Function,
+    ) -> Result<(), js_sys::Error> {
+        let options: tiles::JsPngStreamOptions = options::parse_options(options)?;
+        self.render_png_stream_inner(&options, |chunk| {
+            let chunk = js_sys::Uint8Array::from(chunk.as_slice());
+            callback.call1(&JsValue::NULL, &chunk)?;
+            Ok(())
+        })
+    }
+
     /// Output usvg-simplified SVG string
     #[wasm_bindgen(js_name = toString)]
     #[allow(clippy::inherent_to_string)]
@@ -441,33 +1078,10 @@ impl Resvg {
 
     /// Calculate a maximum bounding box of all visible elements in this SVG.
     ///
-    /// Note: path bounding box are approx values.
+    /// Note: path bounding box are approx values, unless `kind` is `ink`.
     #[wasm_bindgen(js_name = innerBBox)]
-    pub fn inner_bbox(&self) -> Option<BBox> {
-        let rect = self.tree.view_box.rect;
-        let rect = points_to_rect(
-            Vector2F::new(rect.x(), rect.y()),
-            Vector2F::new(rect.right(), rect.bottom()),
-        );
-        let mut v = None;
-        for child in self.tree.root.children() {
-            let child_viewbox = match self.node_bbox(child).and_then(|v| v.intersection(rect)) {
-                Some(v) => v,
-                None => continue,
-            };
-            if let Some(v) = v.as_mut() {
-                *v = child_viewbox.union_rect(*v);
-            } else {
-                v = Some(child_viewbox)
-            };
-        }
-        let v = v?;
-        Some(BBox {
-            x: v.min_x().floor() as f64,
-            y: v.min_y().floor() as f64,
-            width: (v.max_x().ceil() - v.min_x().floor()) as f64,
-            height: (v.max_y().ceil() - v.min_y().floor()) as f64,
-        })
+    pub fn inner_bbox(&self, options: Option<String>) -> Result<Option<BBox>, js_sys::Error> {
+        Ok(self.inner_bbox_inner(options)?)
     }
 
     #[wasm_bindgen(js_name = getBBox)]
@@ -484,16 +1098,100 @@ impl Resvg {
         })
     }
 
+    /// Calculate the bounding box of the element with `id` in user space.
+    #[wasm_bindgen(js_name = getElementBBox)]
+    pub fn get_element_bbox(
+        &self,
+        id: String,
+        options: Option<String>,
+    ) -> Result<Option<BBox>, js_sys::Error> {
+        Ok(self.get_element_bbox_inner(&id, options)?)
+    }
+
+    /// Calculate the bounding box of every element with an id, in user space and in output pixels.
+    #[wasm_bindgen(js_name = elementBBoxes)]
+    pub fn element_bboxes(&self, options: Option<String>) -> Result<js_sys::Object, js_sys::Error> {
+        let result = js_sys::Object::new();
+        for (id, bbox) in self.element_bboxes_inner(options)? {
+            let object = js_sys::Object::new();
+            js_sys::Reflect::set(&object, &"user".into(), &bbox.user.into())?;
+            js_sys::Reflect::set(&object, &"pixel".into(), &bbox.pixel.into())?;
+            js_sys::Reflect::set(&result, &id.into(), &object)?;
+        }
+        Ok(result)
+    }
+
+    /// Get the ids of the elements painted at the pixel `(x, y)` of the rendered image,
+    /// from the outermost to the innermost.
+    #[wasm_bindgen(js_name = elementsAtPoint)]
+    pub fn elements_at_point(&self, x: f64, y: f64) -> Result<js_sys::Array, js_sys::Error> {
+        let ids = self.elements_at_point_inner(x, y)?;
+        Ok(js_sys::Array::from_iter(ids.into_iter().map(JsValue::from)))
+    }
+
     #[wasm_bindgen(js_name = cropByBBox)]
-    /// Use a given `BBox` to crop the svg. Currently this method simply changes
-    /// the viewbox/size of the svg and do not move the elements for simplicity
+    /// Use a given `BBox` to crop the svg. By default this method simply changes
+    /// the viewbox/size of the svg, `{ normalize: true }` also moves the elements to 0,0
     ///
     /// # Arguments
     /// * `bbox` - The bounding box to crop to
     /// * `padding` - Optional bleed area around the crop box (default: 0.0)
     /// * `square` - Optional flag to make the crop area square using the larger dimension (default: false)
-    pub fn crop_by_bbox(&mut self, bbox: &BBox, padding: Option<f64>, square: Option<bool>) {
-        self.crop_by_bbox_inner(bbox, padding, square);
+    /// * `relative` - Optional flag to position `bbox` relative to the current crop instead of the user space (default: false)
+    pub fn crop_by_bbox(
+        &mut self,
+        bbox: &BBox,
+        padding: Option<f64>,
+        square: Option<bool>,
+        relative: Option<bool>,
+    ) {
+        self.crop_by_bbox_inner(
+            bbox,
+            &options::JsCropByBBoxOptions {
+                padding: options::JsCropPadding::Uniform(padding.unwrap_or(0.0)),
+                square: square.unwrap_or(false),
+                relative: relative.unwrap_or(false),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Use a given `BBox` to crop the svg, with per side padding, an aspect ratio,
+    /// or moving the content to 0,0
+    #[wasm_bindgen(js_name = cropByBBoxWithOptions)]
+    pub fn crop_by_bbox_with_options(
+        &mut self,
+        bbox: &BBox,
+        options: Option<String>,
+    ) -> Result<(), js_sys::Error> {
+        let options = options::parse_options(options)?;
+        self.crop_by_bbox_inner(bbox, &options);
+        Ok(())
+    }
+
+    /// Restore the viewbox and size of the SVG before `cropByBBox()`
+    #[wasm_bindgen(js_name = resetCrop)]
+    pub fn reset_crop(&mut self) {
+        self.reset_crop_inner();
+    }
+
+    /// Get the area cropped by `cropByBBox()` in user space, including the padding
+    #[wasm_bindgen(js_name = getCrop)]
+    pub fn get_crop(&self) -> Option<BBox> {
+        self.get_crop_inner()
+    }
+
+    /// Get all text chunks with their font, fill and bounding box in user space.
+    #[wasm_bindgen(js_name = textContent)]
+    pub fn text_content(&self) -> js_sys::Array {
+        let chunks = text::text_chunks(&self.texts, &self.tree, &self.fontdb);
+        js_sys::Array::from_iter(chunks.into_iter().map(JsValue::from))
+    }
+
+    /// Get the warnings logged while parsing, rendering and resolving images.
+    pub fn warnings(&self) -> js_sys::Array {
+        let warnings = self.warnings.borrow();
+        js_sys::Array::from_iter(warnings.iter().cloned().map(JsValue::from))
     }
 
     #[wasm_bindgen(js_name = imagesToResolve)]
@@ -515,7 +1213,10 @@ impl Resvg {
 }
 
 impl Resvg {
-    fn node_bbox(&self, node: usvg::Node) -> Option<RectF> {
+    /// Calculates the bounding box of `node` in its parent's coordinates.
+    ///
+    /// `Visual` skips unpainted paths and clips the groups to their clip path, mask and the canvas.
+    fn node_bbox(&self, node: usvg::Node, kind: BBoxKind) -> Option<RectF> {
         let transform = node.borrow().transform();
         let bbox = match &*node.borrow() {
             usvg::NodeKind::Path(p) => {
@@ -529,9 +1230,25 @@ impl Resvg {
                         .as_ref()
                         .map(|f| f.opacity.get() == 0.0)
                         .unwrap_or_default();
-                if no_fill && no_stroke {
+                if matches!(kind, BBoxKind::Visual | BBoxKind::Ink) && no_fill && no_stroke {
                     return None;
                 }
+                if kind == BBoxKind::Ink {
+                    // Stroked by tiny-skia, the same way resvg paints it.
+                    let fill = (!no_fill).then(|| p.data.bounds());
+                    let stroke = p
+                        .stroke
+                        .as_ref()
+                        .filter(|_| !no_stroke)
+                        .and_then(|s| p.data.stroke(&hit::to_tiny_skia_stroke(s), 1.0))
+                        .map(|outline| outline.bounds());
+                    let bbox = [fill, stroke]
+                        .into_iter()
+                        .flatten()
+                        .map(skia_rect)
+                        .reduce(|a, b| a.union_rect(b))?;
+                    return Some(transform_rect(bbox, transform));
+                }
                 let mut outline = Outline::new();
                 let mut contour = Contour::new();
                 let mut iter = p.data.segments().peekable();
@@ -575,7 +1292,12 @@ impl Resvg {
                     outline.push_contour(std::mem::replace(&mut contour, Contour::new()));
                 }
                 if let Some(stroke) = p.stroke.as_ref() {
-                    if !no_stroke {
+                    let with_stroke = match kind {
+                        BBoxKind::Fill => false,
+                        BBoxKind::Stroke => true,
+                        BBoxKind::Visual | BBoxKind::Ink => !no_stroke,
+                    };
+                    if with_stroke {
                         let mut style = StrokeStyle::default();
                         style.line_width = stroke.width.get();
                         style.line_join = LineJoin::Miter(style.line_width);
@@ -591,29 +1313,41 @@ impl Resvg {
                 }
                 Some(outline.bounds())
             }
+            usvg::NodeKind::Group(_) if !matches!(kind, BBoxKind::Visual | BBoxKind::Ink) => node
+                .children()
+                .filter_map(|child| self.node_bbox(child, kind))
+                .reduce(|a, b| a.union_rect(b)),
             usvg::NodeKind::Group(g) => {
                 let clippath = if let Some(clippath) =
                     g.clip_path.as_ref().and_then(|n| n.root.first_child())
                 {
-                    self.node_bbox(clippath)
+                    self.node_bbox(clippath, kind)
                 } else if let Some(mask) = g.mask.as_ref().and_then(|n| n.root.first_child()) {
-                    self.node_bbox(mask)
+                    self.node_bbox(mask, kind)
                 } else {
                     Some(self.viewbox())
                 }?;
                 let mut v = None;
                 for child in node.children() {
-                    let child_viewbox =
-                        match self.node_bbox(child).and_then(|v| v.intersection(clippath)) {
-                            Some(v) => v,
-                            None => continue,
-                        };
+                    let child_viewbox = match self
+                        .node_bbox(child, kind)
+                        .and_then(|v| v.intersection(clippath))
+                    {
+                        Some(v) => v,
+                        None => continue,
+                    };
                     if let Some(v) = v.as_mut() {
                         *v = child_viewbox.union_rect(*v);
                     } else {
                         v = Some(child_viewbox)
                     };
                 }
+                if kind == BBoxKind::Ink && !g.filters.is_empty() {
+                    // The filters paint their whole region, which can be larger than the content.
+                    v = self
+                        .filter_region(&node, g)
+                        .and_then(|v| v.intersection(clippath));
+                }
                 v.and_then(|v| v.intersection(self.viewbox()))
             }
             usvg::NodeKind::Image(image) => {
@@ -625,19 +1359,154 @@ impl Resvg {
             }
             usvg::NodeKind::Text(_) => None,
         }?;
-        let mut pts = vec![
-            Point::from_xy(bbox.min_x(), bbox.min_y()),
-            Point::from_xy(bbox.max_x(), bbox.max_y()),
-            Point::from_xy(bbox.min_x(), bbox.max_y()),
-            Point::from_xy(bbox.max_x(), bbox.min_y()),
-        ];
-        transform.map_points(&mut pts);
-        let x_min = pts[0].x.min(pts[1].x).min(pts[2].x).min(pts[3].x);
-        let x_max = pts[0].x.max(pts[1].x).max(pts[2].x).max(pts[3].x);
-        let y_min = pts[0].y.min(pts[1].y).min(pts[2].y).min(pts[3].y);
-        let y_max = pts[0].y.max(pts[1].y).max(pts[2].y).max(pts[3].y);
-        let r = points_to_rect(Vector2F::new(x_min, y_min), Vector2F::new(x_max, y_max));
-        Some(r)
+        Some(transform_rect(bbox, transform))
+    }
+
+    /// Calculates the union of the filter regions of the group `node`, in its own coordinates.
+    fn filter_region(&self, node: &usvg::Node, group: &usvg::Group) -> Option<RectF> {
+        let object_bbox = || {
+            let bbox = node
+                .children()
+                .filter_map(|child| self.node_bbox(child, BBoxKind::Fill))
+                .reduce(|a, b| a.union_rect(b))?;
+            usvg::NonZeroRect::from_xywh(bbox.min_x(), bbox.min_y(), bbox.width(), bbox.height())
+        };
+        group
+            .filters
+            .iter()
+            .filter_map(|filter| match filter.units {
+                usvg::Units::UserSpaceOnUse => Some(filter.rect),
+                // Like resvg, the filter is skipped if the element has no bounding box.
+                usvg::Units::ObjectBoundingBox => Some(filter.rect.bbox_transform(object_bbox()?)),
+            })
+            .map(|rect| skia_rect(rect.to_rect()))
+            .reduce(|a, b| a.union_rect(b))
+    }
+
+    /// Calculates the bounding box of `node` in user space.
+    fn element_bbox(&self, node: &usvg::Node, kind: BBoxKind) -> Option<RectF> {
+        let bbox = self.node_bbox(node.clone(), kind)?;
+        let transform = node.parent().map(|p| p.abs_transform()).unwrap_or_default();
+        Some(transform_rect(bbox, transform))
+    }
+
+    /// Maps user space to the pixels of `render()`, after `fitTo` and the `crop` option.
+    fn pixel_transform(&self) -> Result<resvg::tiny_skia::Transform, Error> {
+        let (width, height, transform) = self.js_options.fit_to.fit_to(self.tree.size)?;
+        let transform = self.user_transform(transform);
+        let crop = self.js_options.crop.resolve(width, height, transform)?;
+        Ok(transform.post_translate(-crop.x() as f32, -crop.y() as f32))
+    }
+
+    /// Maps user space to the pixels of the uncropped image rendered with `fit_transform`.
+    fn user_transform(
+        &self,
+        fit_transform: resvg::tiny_skia::Transform,
+    ) -> resvg::tiny_skia::Transform {
+        fit_transform.pre_concat(self.view_box_transform())
+    }
+
+    /// The transform applied by `resvg::Tree::render()` before the one it is given.
+    fn view_box_transform(&self) -> resvg::tiny_skia::Transform {
+        usvg::utils::view_box_to_transform(
+            self.tree.view_box.rect,
+            self.tree.view_box.aspect,
+            self.tree.size,
+        )
+    }
+
+    /// The size of `renderRegion()` and the transform to pass to `render_tree()`.
+    fn region_transform(
+        &self,
+        region: &BBox,
+        options: &options::JsRenderRegionOptions,
+    ) -> Result<(u32, u32, resvg::tiny_skia::Transform), Error> {
+        let (width, height, transform) =
+            options.resolve(region.x, region.y, region.width, region.height)?;
+        // Cancel the viewbox, the region is in user space.
+        let view_box = self.view_box_transform().invert().unwrap_or_default();
+        Ok((width, height, transform.pre_concat(view_box)))
+    }
+
+    fn inner_bbox_inner(&self, options: Option<String>) -> Result<Option<BBox>, Error> {
+        let options: options::JsElementBBoxOptions = options::parse_options(options)?;
+        let kind = options.kind.unwrap_or(BBoxKind::Visual);
+        let rect = self.tree.view_box.rect;
+        let rect = points_to_rect(
+            Vector2F::new(rect.x(), rect.y()),
+            Vector2F::new(rect.right(), rect.bottom()),
+        );
+        let mut v = None;
+        for child in self.tree.root.children() {
+            let child_viewbox = match self
+                .node_bbox(child, kind)
+                .and_then(|v| v.intersection(rect))
+            {
+                Some(v) => v,
+                None => continue,
+            };
+            if let Some(v) = v.as_mut() {
+                *v = child_viewbox.union_rect(*v);
+            } else {
+                v = Some(child_viewbox)
+            };
+        }
+        Ok(v.map(|v| BBox {
+            x: v.min_x().floor() as f64,
+            y: v.min_y().floor() as f64,
+            width: (v.max_x().ceil() - v.min_x().floor()) as f64,
+            height: (v.max_y().ceil() - v.min_y().floor()) as f64,
+        }))
+    }
+
+    fn get_element_bbox_inner(
+        &self,
+        id: &str,
+        options: Option<String>,
+    ) -> Result<Option<BBox>, Error> {
+        let options: options::JsElementBBoxOptions = options::parse_options(options)?;
+        let node = self
+            .tree
+            .node_by_id(id)
+            .ok_or_else(|| Error::ElementNotFound(id.to_string()))?;
+        Ok(self
+            .element_bbox(&node, options.kind.unwrap_or_default())
+            .map(BBox::from))
+    }
+
+    /// The bounding box of every element with an id, in user space and in output pixels.
+    fn element_bboxes_inner(
+        &self,
+        options: Option<String>,
+    ) -> Result<BTreeMap<String, ElementBBox>, Error> {
+        let options: options::JsElementBBoxOptions = options::parse_options(options)?;
+        let pixel_transform = self.pixel_transform()?;
+        let mut bboxes = BTreeMap::new();
+        for node in self.tree.root.descendants() {
+            let id = node.id().to_string();
+            if id.is_empty() || bboxes.contains_key(&id) {
+                continue;
+            }
+            if let Some(bbox) = self.element_bbox(&node, options.kind.unwrap_or_default()) {
+                let element_bbox = ElementBBox {
+                    user: BBox::from(bbox),
+                    pixel: BBox::from(transform_rect(bbox, pixel_transform)),
+                };
+                bboxes.insert(id, element_bbox);
+            }
+        }
+        Ok(bboxes)
+    }
+
+    /// The ids of the elements painted at a pixel of `render()`.
+    fn elements_at_point_inner(&self, x: f64, y: f64) -> Result<Vec<String>, Error> {
+        let pixel_transform = self.pixel_transform()?;
+        Ok(hit::elements_at_point(
+            &self.tree,
+            pixel_transform,
+            x as f32,
+            y as f32,
+        ))
     }
 
     fn viewbox(&self) -> RectF {
@@ -647,7 +1516,70 @@ impl Resvg {
         )
     }
 
-    fn crop_by_bbox_inner(&mut self, bbox: &BBox, padding: Option<f64>, square: Option<bool>) {
+    fn reset_crop_inner(&mut self) {
+        self.denormalize();
+        self.tree.view_box = self.original_view_box;
+        self.tree.size = self.original_size;
+        self.cropped_empty = false;
+    }
+
+    fn get_crop_inner(&self) -> Option<BBox> {
+        let rect = self.crop_rect();
+        let cropped = rect != self.original_view_box.rect || self.tree.size != self.original_size;
+        cropped.then(|| BBox {
+            x: rect.x() as f64,
+            y: rect.y() as f64,
+            width: rect.width() as f64,
+            height: rect.height() as f64,
+        })
+    }
+
+    /// The current viewbox in the coordinates of the original SVG.
+    fn crop_rect(&self) -> usvg::NonZeroRect {
+        let rect = self.tree.view_box.rect;
+        match &self.normalized {
+            Some((_, dx, dy)) => rect
+                .translate_to(rect.x() + dx, rect.y() + dy)
+                .unwrap_or(rect),
+            None => rect,
+        }
+    }
+
+    /// Moves the content of a `normalize` crop back to its original coordinates.
+    fn denormalize(&mut self) {
+        if let Some((group, dx, dy)) = self.normalized.take() {
+            for child in group.children().collect::<Vec<_>>() {
+                child.detach();
+                self.tree.root.append(child);
+            }
+            group.detach();
+            let rect = self.tree.view_box.rect;
+            if let Some(rect) = rect.translate_to(rect.x() + dx, rect.y() + dy) {
+                self.tree.view_box.rect = rect;
+            }
+        }
+    }
+
+    /// Moves the content so that the viewbox starts at 0,0.
+    fn normalize(&mut self) {
+        let rect = self.tree.view_box.rect;
+        let (dx, dy) = (rect.x(), rect.y());
+        let group = usvg::Node::new(NodeKind::Group(usvg::Group {
+            transform: resvg::tiny_skia::Transform::from_translate(-dx, -dy),
+            ..usvg::Group::default()
+        }));
+        for child in self.tree.root.children().collect::<Vec<_>>() {
+            child.detach();
+            group.append(child);
+        }
+        self.tree.root.append(group.clone());
+        if let Some(rect) = usvg::NonZeroRect::from_xywh(0.0, 0.0, rect.width(), rect.height()) {
+            self.tree.view_box.rect = rect;
+        }
+        self.normalized = Some((group, dx, dy));
+    }
+
+    fn crop_by_bbox_inner(&mut self, bbox: &BBox, options: &options::JsCropByBBoxOptions) {
         // Validate bbox dimensions - reject non-finite or non-positive values
         if !bbox.width.is_finite()
             || !bbox.height.is_finite()
@@ -657,29 +1589,40 @@ impl Resvg {
             return;
         }
 
-        // Validate padding - reject NaN, Infinity, and negative values
-        let pixel_padding = match padding {
-            Some(val) if val.is_finite() && val >= 0.0 => val as f32,
-            _ => 0.0, // NaN, Infinity, negative, or None -> use 0.0
-        };
-        let square = square.unwrap_or(false);
+        let padding = options.padding.sides();
+        // Reject NaN, Infinity, zero, and negative ratios
+        let aspect_ratio = options
+            .aspect_ratio
+            .filter(|ratio| ratio.is_finite() && *ratio > 0.0)
+            .map(|ratio| ratio as f32)
+            .or(options.square.then_some(1.0));
 
         let mut x = bbox.x as f32;
         let mut y = bbox.y as f32;
+        if options.relative {
+            let rect = self.crop_rect();
+            x += rect.x();
+            y += rect.y();
+        }
         let mut width = bbox.width as f32;
         let mut height = bbox.height as f32;
 
-        if square && width != height {
-            let max_dimension = width.max(height);
-            let width_diff = max_dimension - width;
-            let height_diff = max_dimension - height;
-
-            x -= width_diff / 2.0;
-            y -= height_diff / 2.0;
-            width = max_dimension;
-            height = max_dimension;
+        // Grow the smaller dimension around the center.
+        if let Some(ratio) = aspect_ratio {
+            if width / height < ratio {
+                let new_width = height * ratio;
+                x -= (new_width - width) / 2.0;
+                width = new_width;
+            } else if width / height > ratio {
+                let new_height = width / ratio;
+                y -= (new_height - height) / 2.0;
+                height = new_height;
+            }
         }
 
+        // The crop is computed in the coordinates of the original SVG.
+        self.denormalize();
+
         // Reset flag for safety in case future branches skip apply_crop().
         self.cropped_empty = false;
 
@@ -709,7 +1652,7 @@ impl Resvg {
             y,
             width,
             height,
-            padding: pixel_padding,
+            padding,
         };
 
         match &self.js_options.fit_to {
@@ -721,15 +1664,20 @@ impl Resvg {
                     let mut target_width = target_width as f32;
                     let mut target_height = target_height as f32;
 
-                    if square {
-                        let side = match &self.js_options.fit_to {
-                            options::FitToDef::Width(_) => target_width,
-                            options::FitToDef::Height(_) => target_height,
-                            options::FitToDef::Zoom(_) => target_width.max(target_height),
-                            options::FitToDef::Original => target_width.max(target_height),
-                        };
-                        target_width = side;
-                        target_height = side;
+                    // Make the output exactly match the ratio, despite the rounding of `fit_to()`.
+                    if let Some(ratio) = aspect_ratio {
+                        match &self.js_options.fit_to {
+                            options::FitToDef::Width(_) => {
+                                target_height = (target_width / ratio).round()
+                            }
+                            options::FitToDef::Height(_) => {
+                                target_width = (target_height * ratio).round()
+                            }
+                            _ if target_width / target_height < ratio => {
+                                target_width = (target_height * ratio).round()
+                            }
+                            _ => target_height = (target_width / ratio).round(),
+                        }
                     }
 
                     let (size_width, size_height) = match &self.js_options.fit_to {
@@ -765,26 +1713,76 @@ impl Resvg {
                 }
             },
         }
+
+        if options.normalize {
+            self.normalize();
+        }
+    }
+
+    /// Checks the `renderTimeout` limit between the steps of the parse.
+    fn check_deadline(deadline: Option<&limits::Deadline>) -> Result<(), Error> {
+        match deadline {
+            Some(deadline) => deadline.check(),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the limits that can only be known once the SVG is parsed.
+    fn check_parsed(
+        tree: &usvg::Tree,
+        js_options: &JsOptions,
+        deadline: Option<&limits::Deadline>,
+    ) -> Result<(), Error> {
+        Self::check_deadline(deadline)?;
+        // A zero sized target is only reported on render.
+        if let Ok((width, height, _)) = js_options.fit_to.fit_to(tree.size) {
+            js_options.limits.check_output(width, height)?;
+        }
+        Ok(())
+    }
+
+    fn add_warnings(&self, warnings: Vec<logger::Warning>) {
+        let mut all = self.warnings.borrow_mut();
+        for mut warning in warnings {
+            warning.resolve_element_id(&self.tree);
+            all.push(warning);
+        }
     }
 
     fn render_inner(&self) -> Result<RenderedImage, Error> {
+        let collector = logger::WarningCollector::start(self.js_options.log_level);
+        let rendered = self.render_image();
+        self.add_warnings(collector.finish());
+        rendered
+    }
+
+    fn render_image(&self) -> Result<RenderedImage, Error> {
         let (width, height, transform) = self.js_options.fit_to.fit_to(self.tree.size)?;
+        self.js_options.limits.check_output(width, height)?;
+        // Validate the crop before spending time on rendering.
+        let crop_rect =
+            self.js_options
+                .crop
+                .resolve(width, height, self.user_transform(transform))?;
         let mut pixmap = self.js_options.create_pixmap(width, height)?;
         // Skip rendering when the crop yields no visible content.
         if !self.cropped_empty {
-            resvg::Tree::from_usvg(&self.tree).render(transform, &mut pixmap.as_mut());
+            self.render_tree(&self.tree, transform, &mut pixmap)?;
         }
 
         // Crop the SVG
-        let crop_rect = resvg::tiny_skia::IntRect::from_ltrb(
-            self.js_options.crop.left,
-            self.js_options.crop.top,
-            self.js_options.crop.right.unwrap_or(width as i32),
-            self.js_options.crop.bottom.unwrap_or(height as i32),
-        );
+        if crop_rect.width() != width || crop_rect.height() != height {
+            pixmap = pixmap
+                .clone_rect(crop_rect)
+                .ok_or(Error::AllocationFailed)?;
+        }
 
-        if let Some(crop_rect) = crop_rect {
-            pixmap = pixmap.clone_rect(crop_rect).unwrap_or(pixmap);
+        // Trim the transparent pixels, fully transparent images are kept as they are.
+        if let Some(options) = &self.js_options.trim {
+            if let Some((bounds, width, height)) = trim::trimmed_size(&pixmap, options) {
+                self.js_options.limits.check_output(width, height)?;
+                pixmap = trim::trim(&pixmap, bounds, width, height, options.padding)?;
+            }
         }
 
         Ok(RenderedImage {
@@ -794,6 +1792,275 @@ impl Resvg {
         })
     }
 
+    /// The part of the image covered by `renderTiles()` and `renderPngStream()`,
+    /// with the transform of the uncropped image.
+    fn tiled_area(
+        &self,
+        fit_to: Option<&options::FitToDef>,
+    ) -> Result<(resvg::tiny_skia::IntRect, resvg::tiny_skia::Transform), Error> {
+        let fit_to = fit_to.unwrap_or(&self.js_options.fit_to);
+        let (width, height, transform) = fit_to.fit_to(self.tree.size)?;
+        let area = self
+            .js_options
+            .crop
+            .resolve(width, height, self.user_transform(transform))?;
+        self.js_options
+            .limits
+            .check_output(area.width(), area.height())?;
+        Ok((area, transform))
+    }
+
+    /// Renders the `rect` part of the image drawn with `transform`.
+    fn render_part(
+        &self,
+        transform: resvg::tiny_skia::Transform,
+        rect: resvg::tiny_skia::IntRect,
+    ) -> Result<Pixmap, Error> {
+        let mut pixmap = self.js_options.create_pixmap(rect.width(), rect.height())?;
+        if !self.cropped_empty {
+            let transform = transform.post_translate(-rect.x() as f32, -rect.y() as f32);
+            self.render_tree(&self.tree, transform, &mut pixmap)?;
+        }
+        Ok(pixmap)
+    }
+
+    /// Renders the tiles one by one, only the current tile is held in memory.
+    fn render_tiles_inner<E: From<Error>>(
+        &self,
+        options: &tiles::JsRenderTilesOptions,
+        mut emit: impl FnMut(resvg::tiny_skia::IntRect, Pixmap) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let collector = logger::WarningCollector::start(self.js_options.log_level);
+        let rendered = (|| -> Result<(), E> {
+            let (area, transform) = self.tiled_area(options.fit_to.as_ref())?;
+            let size = options.tile_size.get();
+            for tile in tiles::tile_grid(area.width(), area.height(), size, size) {
+                let rect = tile
+                    .translate(area.x(), area.y())
+                    .ok_or(Error::AllocationFailed)?;
+                emit(tile, self.render_part(transform, rect)?)?;
+            }
+            Ok(())
+        })();
+        self.add_warnings(collector.finish());
+        rendered
+    }
+
+    /// Renders the levels of a Deep Zoom pyramid from the vector tree, tile by tile.
+    fn export_tile_pyramid_inner<E: From<Error>>(
+        &self,
+        options: &tiles::JsTilePyramidOptions,
+        mut emit: impl FnMut(tiles::PyramidTile, Pixmap) -> Result<(), E>,
+    ) -> Result<(tiles::Pyramid, u32, u32), E> {
+        let collector = logger::WarningCollector::start(self.js_options.log_level);
+        let rendered = (|| -> Result<_, E> {
+            let (area, transform) = self.tiled_area(None)?;
+            let transform = transform.post_translate(-area.x() as f32, -area.y() as f32);
+            let pyramid = tiles::Pyramid::new(area.width(), area.height());
+            let max_level = options
+                .max_level
+                .map_or(pyramid.full_level, |level| level.min(pyramid.full_level));
+            if options.min_level > max_level {
+                return Err(Error::InvalidLevels(options.min_level, max_level).into());
+            }
+            for level in options.min_level..=max_level {
+                let scale = pyramid.scale(level);
+                let transform = transform.post_scale(scale, scale);
+                let size = options.tile_size.get();
+                for tile in pyramid.tiles(level, size, options.overlap) {
+                    let pixmap = self.render_part(transform, tile.rect)?;
+                    emit(tile, pixmap)?;
+                }
+            }
+            Ok((pyramid, options.min_level, max_level))
+        })();
+        self.add_warnings(collector.finish());
+        rendered
+    }
+
+    fn render_png_stream_inner<E: From<Error>>(
+        &self,
+        options: &tiles::JsPngStreamOptions,
+        emit: impl FnMut(Vec<u8>) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let collector = logger::WarningCollector::start(self.js_options.log_level);
+        let rendered = (|| -> Result<(), E> {
+            let (area, transform) = self.tiled_area(options.fit_to.as_ref())?;
+            let mut out = tiles::ChunkWriter::new(emit);
+            tiles::write_png(
+                &mut out,
+                area.width(),
+                area.height(),
+                options.strip_height.get(),
+                |strip| {
+                    let rect = strip
+                        .translate(area.x(), area.y())
+                        .ok_or(Error::AllocationFailed)?;
+                    Ok(self.render_part(transform, rect)?)
+                },
+            )
+        })();
+        self.add_warnings(collector.finish());
+        rendered
+    }
+
+    /// Renders a region of user space, the viewbox, `fitTo` and `crop` are ignored.
+    fn render_region_inner(
+        &self,
+        region: &BBox,
+        options: Option<String>,
+    ) -> Result<RenderedImage, Error> {
+        let options: options::JsRenderRegionOptions = options::parse_options(options)?;
+        let collector = logger::WarningCollector::start(self.js_options.log_level);
+        let rendered = (|| {
+            let (width, height, transform) = self.region_transform(region, &options)?;
+            self.js_options.limits.check_output(width, height)?;
+            let mut pixmap = self.js_options.create_pixmap(width, height)?;
+            self.render_tree(&self.tree, transform, &mut pixmap)?;
+            Ok(RenderedImage {
+                pix: pixmap,
+                #[cfg(not(target_arch = "wasm32"))]
+                accounted_bytes: 0,
+            })
+        })();
+        self.add_warnings(collector.finish());
+        rendered
+    }
+
+    /// Renders `tree` onto `pixmap`, within the `renderTimeout` limit.
+    fn render_tree(
+        &self,
+        tree: &usvg::Tree,
+        transform: resvg::tiny_skia::Transform,
+        pixmap: &mut Pixmap,
+    ) -> Result<(), Error> {
+        match self.js_options.limits.render_timeout {
+            Some(timeout) => {
+                render_with_deadline(tree, transform, pixmap, &limits::Deadline::new(timeout))
+            }
+            None => {
+                resvg::Tree::from_usvg(tree).render(transform, &mut pixmap.as_mut());
+                Ok(())
+            }
+        }
+    }
+
+    fn render_element_inner(
+        &self,
+        id: &str,
+        options: Option<String>,
+    ) -> Result<RenderedImage, Error> {
+        let options: options::JsRenderElementOptions = options::parse_options(options)?;
+        let collector = logger::WarningCollector::start(self.js_options.log_level);
+        let tree = match element::element_tree(&self.tree, id, options.padding) {
+            // `<symbol>` elements are not part of the rendered tree.
+            Err(Error::ElementNotFound(missing)) => {
+                self.symbols()
+                    .and_then(|symbols| match symbols.iter().find(|s| s.id == missing) {
+                        Some(symbol) => symbol.element_tree(options.padding),
+                        None => Err(Error::ElementNotFound(missing)),
+                    })
+            }
+            tree => tree,
+        };
+        let rendered = tree.and_then(|tree| self.render_view(&tree, &options.fit_to));
+        self.add_warnings(collector.finish());
+        rendered
+    }
+
+    /// Renders every `<symbol>` and top-level group with an id, in this order.
+    fn render_symbols_inner(
+        &self,
+        options: &sprite::JsRenderSymbolsOptions,
+    ) -> Result<Vec<(String, RenderedImage)>, Error> {
+        let collector = logger::WarningCollector::start(self.js_options.log_level);
+        let rendered = (|| {
+            let mut images = vec![];
+            for symbol in self.symbols()? {
+                if let Some(tree) = symbol.tree() {
+                    images.push((symbol.id.clone(), self.render_view(&tree, &options.fit_to)?));
+                }
+            }
+            for child in self.tree.root.children() {
+                let id = match &*child.borrow() {
+                    NodeKind::Group(g) if !g.id.is_empty() => g.id.clone(),
+                    _ => continue,
+                };
+                match element::element_tree(&self.tree, &id, 0.0) {
+                    Ok(tree) => images.push((id, self.render_view(&tree, &options.fit_to)?)),
+                    // Skip the empty groups.
+                    Err(Error::EmptyElement(_)) => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(images)
+        })();
+        self.add_warnings(collector.finish());
+        rendered
+    }
+
+    /// The `<symbol>` elements, parsed on the first call.
+    fn symbols(&self) -> Result<&[sprite::Symbol], Error> {
+        if let Some(symbols) = self.symbols.get() {
+            return Ok(symbols);
+        }
+        let symbols = self.parse_symbols()?;
+        self.symbol_source.take();
+        Ok(self.symbols.get_or_init(|| symbols))
+    }
+
+    /// Parses the `<symbol>` elements, from a copy of the source with a `<use>` appended for each.
+    fn parse_symbols(&self) -> Result<Vec<sprite::Symbol>, Error> {
+        let source = self.symbol_source.borrow();
+        let source = match &*source {
+            Some(source) => source,
+            None => return Ok(vec![]),
+        };
+        let (data, uses) = sprite::inject_symbols(Cow::Borrowed(source));
+        if uses.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut opts = self.js_options.usvg_options();
+        // The images of the document are loaded again, they were counted by the first parse.
+        let limits = options::JsLimitsOptions {
+            max_images: None,
+            ..self.js_options.limits
+        };
+        let deadline = self
+            .js_options
+            .limits
+            .render_timeout
+            .map(limits::Deadline::new);
+        let guard = options::tweak_usvg_options(
+            &mut opts,
+            limits,
+            self.js_options.resources_sandbox,
+            self.resolved_images.clone(),
+            self.fontdb.clone(),
+            deadline,
+        );
+        let tree = usvg::Tree::from_data(&data, &opts)?;
+        guard.finish()?;
+        Ok(sprite::take_symbols(&tree, uses, &self.fontdb))
+    }
+
+    /// Renders a tree built for a single element or symbol, without the `crop` option.
+    fn render_view(
+        &self,
+        tree: &usvg::Tree,
+        fit_to: &options::FitToDef,
+    ) -> Result<RenderedImage, Error> {
+        let (width, height, transform) = fit_to.fit_to(tree.size)?;
+        self.js_options.limits.check_output(width, height)?;
+        let mut pixmap = self.js_options.create_pixmap(width, height)?;
+        self.render_tree(tree, transform, &mut pixmap)?;
+        Ok(RenderedImage {
+            pix: pixmap,
+            #[cfg(not(target_arch = "wasm32"))]
+            accounted_bytes: 0,
+        })
+    }
+
     fn images_to_resolve_inner(&self) -> Result<Vec<String>, Error> {
         let mut data = vec![];
         for node in self.tree.root.descendants() {
@@ -808,9 +2075,15 @@ impl Resvg {
     }
 
     fn resolve_image_inner(&self, href: String, buffer: Vec<u8>) -> Result<(), Error> {
-        let resolver = usvg::ImageHrefResolver::default_data_resolver();
+        let collector = logger::WarningCollector::start(self.js_options.log_level);
         let (options, _) = self.js_options.to_usvg_options();
-        let mime = MimeType::parse(&buffer)?.mime_type().to_string();
+        let mime = MimeType::parse(&buffer)?;
+        self.js_options
+            .limits
+            .check_image(&href, Some(&mime), &buffer)?;
+        let kind = mime.to_image_kind(Arc::new(buffer), &options, &self.fontdb);
+        self.add_warnings(collector.finish());
+        let kind = kind?;
 
         for node in self.tree.root.descendants() {
             if let NodeKind::Image(i) = &mut *node.borrow_mut() {
@@ -821,10 +2094,7 @@ impl Resvg {
                     false
                 };
                 if matched {
-                    let data = (resolver)(&mime, Arc::new(buffer.clone()), &options);
-                    if let Some(kind) = data {
-                        i.kind = kind;
-                    }
+                    i.kind = kind.clone();
                 }
             }
         }
@@ -835,10 +2105,10 @@ impl Resvg {
 #[cfg(not(target_arch = "wasm32"))]
 impl Resvg {
     // 需要确保 panic 策略是 unwind 才可以使 catch_unwind 生效，通常这是 Rust 构建的默认值
-    fn render_inner_catch_unwind(&self) -> Result<RenderedImage, NapiError> {
+    fn render_inner_catch_unwind(&self) -> Result<RenderedImage, Error> {
         match std::panic::catch_unwind(AssertUnwindSafe(|| self.render_inner())) {
-            Ok(result) => result.map_err(Into::into),
-            Err(panic) => Err(Error::RenderPanic(panic_to_string(panic)).into()),
+            Ok(result) => result,
+            Err(panic) => Err(Error::RenderPanic(panic_to_string(panic))),
         }
     }
 }
@@ -854,10 +2124,208 @@ fn panic_to_string(panic: Box<dyn Any + Send>) -> String {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+#[napi]
+/// Measure a single line of text with the same fonts and shaping used to render SVG text
+pub fn measure_text(
+    env: Env,
+    text: String,
+    options: Option<String>,
+) -> Result<text::TextMetrics, NapiError> {
+    let measure = || {
+        let options: options::JsMeasureTextOptions = options::parse_options(options)?;
+        let fontdb = fonts::load_fonts(&options.font);
+        text::measure_text(&text, &options, &fontdb)
+    };
+    measure().map_err(|e| e.into_napi(&env))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = measureText)]
+/// Measure a single line of text with the same fonts and shaping used to render SVG text
+pub fn measure_text(
+    text: String,
+    options: Option<String>,
+    custom_font_buffers: Option<js_sys::Array>,
+) -> Result<text::TextMetrics, js_sys::Error> {
+    let options: options::JsMeasureTextOptions = options::parse_options(options)?;
+    let mut fontdb = usvg::fontdb::Database::new();
+    fonts::load_wasm_fonts(&options.font, custom_font_buffers, &mut fontdb)?;
+    Ok(text::measure_text(&text, &options, &fontdb)?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[napi]
+/// Forward the log records to `callback` instead of stderr, pass `null` to restore stderr
+pub fn set_logger(
+    env: Env,
+    callback: Option<logger::JsLogger>,
+    level: Option<String>,
+) -> Result<(), NapiError> {
+    if let Some(level) = level {
+        logger::set_level(&level).map_err(|e| e.into_napi(&env))?;
+    }
+    logger::set_js_logger(callback);
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[napi]
+/// Change the log level of the instances without a `logLevel` option
+pub fn set_log_level(env: Env, level: String) -> Result<(), NapiError> {
+    logger::set_level(&level).map_err(|e| e.into_napi(&env))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = setLogger)]
+/// Forward the log records to `callback`, pass `null` to remove it
+pub fn set_logger(
+    callback: Option<js_sys::Function>,
+    level: Option<String>,
+) -> Result<(), js_sys::Error> {
+    if let Some(level) = level {
+        logger::set_level(&level)?;
+    }
+    logger::set_js_logger(callback);
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = setLogLevel)]
+/// Change the log level of the instances without a `logLevel` option
+pub fn set_log_level(level: String) -> Result<(), js_sys::Error> {
+    Ok(logger::set_level(&level)?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[napi]
+/// Renders several SVGs into one sprite sheet, with the position of every SVG
+pub fn pack_sprite_sheet(
+    mut env: Env,
+    svgs: Vec<Either<String, Buffer>>,
+    options: Option<String>,
+    pack_options: Option<String>,
+) -> Result<SpriteSheet, NapiError> {
+    let pack = || {
+        let pack_options: sprite::JsPackOptions = options::parse_options(pack_options)?;
+        let images = svgs
+            .iter()
+            .map(|svg| {
+                let resvg = Resvg::new_inner(svg, options.clone(), None)?;
+                Ok(resvg.render_inner_catch_unwind()?.pix)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        pack_images(images, &pack_options, options)
+    };
+    let mut sheet = pack().map_err(|e| e.into_napi(&env))?;
+    sheet.image.account_external_memory(&mut env)?;
+    Ok(sheet)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = packSpriteSheet)]
+/// Renders several SVGs into one sprite sheet, with the position of every SVG
+pub fn pack_sprite_sheet(
+    svgs: js_sys::Array,
+    options: Option<String>,
+    pack_options: Option<String>,
+    custom_font_buffers: Option<js_sys::Array>,
+) -> Result<js_sys::Object, js_sys::Error> {
+    let pack_options: sprite::JsPackOptions = options::parse_options(pack_options)?;
+    let images = svgs
+        .iter()
+        .map(|svg| {
+            let resvg = Resvg::new(
+                svg.unchecked_into(),
+                options.clone(),
+                custom_font_buffers.clone(),
+                None,
+            )?;
+            Ok(resvg.render_inner()?.pix)
+        })
+        .collect::<Result<Vec<_>, js_sys::Error>>()?;
+    let sheet = pack_images(images, &pack_options, options)?;
+
+    let frames = js_sys::Array::new();
+    for frame in sheet.frames {
+        let object = js_sys::Object::new();
+        js_sys::Reflect::set(&object, &"x".into(), &frame.x.into())?;
+        js_sys::Reflect::set(&object, &"y".into(), &frame.y.into())?;
+        js_sys::Reflect::set(&object, &"width".into(), &frame.width.into())?;
+        js_sys::Reflect::set(&object, &"height".into(), &frame.height.into())?;
+        frames.push(&object);
+    }
+    let result = js_sys::Object::new();
+    js_sys::Reflect::set(&result, &"image".into(), &JsValue::from(sheet.image))?;
+    js_sys::Reflect::set(&result, &"frames".into(), &frames)?;
+    Ok(result)
+}
+
+#[cfg(target_arch = "wasm32")]
+struct SpriteSheet {
+    image: RenderedImage,
+    frames: Vec<sprite::SpriteFrame>,
+}
+
+fn pack_images(
+    images: Vec<Pixmap>,
+    pack_options: &sprite::JsPackOptions,
+    options: Option<String>,
+) -> Result<SpriteSheet, Error> {
+    if images.is_empty() {
+        return Err(Error::ZeroSized);
+    }
+    let js_options: JsOptions = options::parse_options(options)?;
+    let (width, height, frames) =
+        sprite::pack(&images, pack_options.padding, pack_options.max_width);
+    js_options.limits.check_output(width, height)?;
+    Ok(SpriteSheet {
+        image: RenderedImage {
+            pix: sprite::draw(width, height, &images, &frames)?,
+            #[cfg(not(target_arch = "wasm32"))]
+            accounted_bytes: 0,
+        },
+        frames,
+    })
+}
+
+// Called from the libuv worker thread, so the JS callback must be a threadsafe function.
+#[cfg(not(target_arch = "wasm32"))]
+type AsyncImageResolver =
+    ThreadsafeFunction<String, Promise<Option<Buffer>>, String, Status, false>;
+
 #[cfg(not(target_arch = "wasm32"))]
 pub struct AsyncRenderer {
     options: Option<String>,
-    svg: Either<String, Buffer>,
+    svg: Box<dyn ResvgReadable + Send>,
+    image_resolver: Option<AsyncImageResolver>,
+    // The error of `compute()`, turned into a javascript error in `reject()`.
+    error: Option<Error>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AsyncRenderer {
+    // Resolve all images concurrently and block the worker thread until they are done.
+    fn resolve_images(
+        resolver: &AsyncImageResolver,
+        hrefs: Vec<String>,
+    ) -> Result<ResolvedImages, Error> {
+        futures::executor::block_on(async {
+            let buffers = futures::future::try_join_all(
+                hrefs
+                    .iter()
+                    .map(|href| async move { resolver.call_async(href.clone()).await?.await }),
+            )
+            .await?;
+            let mut resolved = ResolvedImages::new();
+            for (href, buffer) in hrefs.into_iter().zip(buffers) {
+                if let Some(buffer) = buffer {
+                    options::add_resolved_image(&mut resolved, href, buffer.to_vec())?;
+                }
+            }
+            Ok::<_, Error>(resolved)
+        })
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -867,8 +2335,21 @@ impl Task for AsyncRenderer {
     type JsValue = RenderedImage;
 
     fn compute(&mut self) -> Result<Self::Output, NapiError> {
-        let resvg = Resvg::new_inner(&self.svg, self.options.clone())?;
-        resvg.render_inner_catch_unwind()
+        let resvg = match &self.image_resolver {
+            Some(resolver) => {
+                let resolve_images =
+                    |hrefs: Vec<String>| AsyncRenderer::resolve_images(resolver, hrefs);
+                Resvg::new_inner(&*self.svg, self.options.clone(), Some(&resolve_images))
+            }
+            None => Resvg::new_inner(&*self.svg, self.options.clone(), None),
+        };
+        resvg
+            .and_then(|resvg| resvg.render_inner_catch_unwind())
+            .map_err(|e| {
+                let reason = format!("{e}");
+                self.error = Some(e);
+                napi::Error::from_reason(reason)
+            })
     }
 
     fn resolve(
@@ -879,6 +2360,13 @@ impl Task for AsyncRenderer {
         result.account_external_memory(&mut env)?;
         Ok(result)
     }
+
+    fn reject(&mut self, env: napi::Env, err: NapiError) -> Result<Self::JsValue, NapiError> {
+        Err(match self.error.take() {
+            Some(e) => e.into_napi(&env),
+            None => err,
+        })
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -887,20 +2375,148 @@ pub fn render_async(
     svg: Either<String, Buffer>,
     options: Option<String>,
     signal: Option<AbortSignal>,
+    image_resolver: Option<AsyncImageResolver>,
+) -> AsyncTask<AsyncRenderer> {
+    AsyncTask::with_optional_signal(
+        AsyncRenderer {
+            options,
+            svg: Box::new(svg),
+            image_resolver,
+            error: None,
+        },
+        signal,
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[napi]
+/// Read and render an SVG or SVGZ file in the libuv thread pool
+pub fn render_file_async(
+    path: String,
+    options: Option<String>,
+    signal: Option<AbortSignal>,
+    image_resolver: Option<AsyncImageResolver>,
 ) -> AsyncTask<AsyncRenderer> {
-    AsyncTask::with_optional_signal(AsyncRenderer { options, svg }, signal)
+    AsyncTask::with_optional_signal(
+        AsyncRenderer {
+            options,
+            svg: Box::new(PathBuf::from(path)),
+            image_resolver,
+            error: None,
+        },
+        signal,
+    )
+}
+
+/// Renders the elements one by one, checking the deadline in between.
+///
+/// The elements are rendered in place, as the root of a tree sharing their nodes, so nothing is
+/// copied. Groups that only have a transform are rendered child by child, other groups are
+/// rendered as a whole since their opacity, clip path, mask or filters apply to all children.
+fn render_with_deadline(
+    tree: &usvg::Tree,
+    transform: resvg::tiny_skia::Transform,
+    pixmap: &mut Pixmap,
+    deadline: &limits::Deadline,
+) -> Result<(), Error> {
+    let view_box =
+        usvg::utils::view_box_to_transform(tree.view_box.rect, tree.view_box.aspect, tree.size);
+    // The view box is applied here, the partial trees only use `transform`.
+    let rect = usvg::NonZeroRect::from_xywh(0.0, 0.0, tree.size.width(), tree.size.height())
+        .ok_or(Error::ZeroSized)?;
+    let part = |root: usvg::Node| usvg::Tree {
+        size: tree.size,
+        view_box: usvg::ViewBox {
+            rect,
+            aspect: usvg::AspectRatio::default(),
+        },
+        root,
+    };
+    render_children_with_deadline(
+        &tree.root,
+        &part,
+        transform.pre_concat(view_box),
+        pixmap,
+        deadline,
+    )?;
+    deadline.check()
+}
+
+fn render_children_with_deadline(
+    parent: &usvg::Node,
+    part: &dyn Fn(usvg::Node) -> usvg::Tree,
+    transform: resvg::tiny_skia::Transform,
+    pixmap: &mut Pixmap,
+    deadline: &limits::Deadline,
+) -> Result<(), Error> {
+    for child in parent.children() {
+        deadline.check()?;
+        let group_transform = match &*child.borrow() {
+            NodeKind::Group(group)
+                if group.opacity.get() == 1.0
+                    && group.blend_mode == usvg::BlendMode::Normal
+                    && !group.isolate
+                    && group.clip_path.is_none()
+                    && group.mask.is_none()
+                    && group.filters.is_empty() =>
+            {
+                Some(group.transform)
+            }
+            _ => None,
+        };
+        match group_transform {
+            Some(group_transform) => render_children_with_deadline(
+                &child,
+                part,
+                transform.pre_concat(group_transform),
+                pixmap,
+                deadline,
+            )?,
+            None => resvg::Tree::from_usvg(&part(child)).render(transform, &mut pixmap.as_mut()),
+        }
+    }
+    Ok(())
 }
 
 fn points_to_rect(min: Vector2F, max: Vector2F) -> RectF {
     RectF::new(min, max - min)
 }
 
+fn skia_rect(rect: resvg::tiny_skia::Rect) -> RectF {
+    points_to_rect(
+        Vector2F::new(rect.left(), rect.top()),
+        Vector2F::new(rect.right(), rect.bottom()),
+    )
+}
+
+/// Calculates the bounding box of `rect` after `transform`.
+fn transform_rect(rect: RectF, transform: resvg::tiny_skia::Transform) -> RectF {
+    let mut pts = vec![
+        Point::from_xy(rect.min_x(), rect.min_y()),
+        Point::from_xy(rect.max_x(), rect.max_y()),
+        Point::from_xy(rect.min_x(), rect.max_y()),
+        Point::from_xy(rect.max_x(), rect.min_y()),
+    ];
+    transform.map_points(&mut pts);
+    let x_min = pts[0].x.min(pts[1].x).min(pts[2].x).min(pts[3].x);
+    let x_max = pts[0].x.max(pts[1].x).max(pts[2].x).max(pts[3].x);
+    let y_min = pts[0].y.min(pts[1].y).min(pts[2].y).min(pts[3].y);
+    let y_max = pts[0].y.max(pts[1].y).max(pts[2].y).max(pts[3].y);
+    points_to_rect(Vector2F::new(x_min, y_min), Vector2F::new(x_max, y_max))
+}
+
 // Detects the file type by magic number.
-// Currently resvg only supports the following types of files.
 pub enum MimeType {
     Png,
     Jpeg,
     Gif,
+    Webp,
+    // Detected, but there is no AVIF decoder.
+    Avif,
+    Bmp,
+    Ico,
+    // SVG or SVGZ
+    Svg,
 }
 
 impl MimeType {
@@ -908,10 +2524,21 @@ impl MimeType {
         if buffer.len() < 4 {
             return Err(Error::UnsupportedImage);
         }
+        if buffer.len() >= 12 && &buffer[0..4] == b"RIFF" && &buffer[8..12] == b"WEBP" {
+            return Ok(MimeType::Webp);
+        }
+        if is_avif(buffer) {
+            return Ok(MimeType::Avif);
+        }
         Ok(match &buffer[0..4] {
             [0x89, 0x50, 0x4E, 0x47] => MimeType::Png,
             [0xFF, 0xD8, 0xFF, _] => MimeType::Jpeg,
             [0x47, 0x49, 0x46, _] => MimeType::Gif,
+            [0x42, 0x4D, _, _] => MimeType::Bmp,
+            [0x00, 0x00, 0x01, 0x00] => MimeType::Ico,
+            // gzip, usvg decompresses SVGZ by itself
+            [0x1F, 0x8B, _, _] => MimeType::Svg,
+            _ if is_svg_text(buffer) => MimeType::Svg,
             _ => return Err(Error::UnsupportedImage),
         })
     }
@@ -920,7 +2547,188 @@ impl MimeType {
         match self {
             MimeType::Png => "image/png",
             MimeType::Gif => "image/gif",
+            MimeType::Webp => "image/webp",
+            MimeType::Avif => "image/avif",
+            MimeType::Bmp => "image/bmp",
+            MimeType::Ico => "image/x-icon",
+            MimeType::Svg => "image/svg+xml",
             _ => "image/jpeg",
         }
     }
+
+    /// Reads the image size from its header, without decoding the pixels.
+    pub(crate) fn dimensions(&self, buffer: &[u8]) -> Option<(u32, u32)> {
+        let be_u16 = |i: usize| Some(u16::from_be_bytes(buffer.get(i..i + 2)?.try_into().ok()?));
+        let le_u16 = |i: usize| Some(u16::from_le_bytes(buffer.get(i..i + 2)?.try_into().ok()?));
+        let be_u32 = |i: usize| Some(u32::from_be_bytes(buffer.get(i..i + 4)?.try_into().ok()?));
+        let le_u32 = |i: usize| Some(u32::from_le_bytes(buffer.get(i..i + 4)?.try_into().ok()?));
+        let le_u24 = |i: usize| Some(le_u32(i)? & 0x00FF_FFFF);
+        match self {
+            MimeType::Png => Some((be_u32(16)?, be_u32(20)?)),
+            MimeType::Gif => Some((le_u16(6)? as u32, le_u16(8)? as u32)),
+            MimeType::Bmp => Some((
+                (le_u32(18)? as i32).unsigned_abs(),
+                (le_u32(22)? as i32).unsigned_abs(),
+            )),
+            MimeType::Ico => {
+                // A width or height of 0 means 256.
+                let count = le_u16(4)? as usize;
+                (0..count)
+                    .filter_map(|i| {
+                        let entry = buffer.get(6 + i * 16..8 + i * 16)?;
+                        let size = |v: u8| if v == 0 { 256 } else { v as u32 };
+                        Some((size(entry[0]), size(entry[1])))
+                    })
+                    .max_by_key(|(w, h)| w * h)
+            }
+            MimeType::Webp => match buffer.get(12..16)? {
+                b"VP8X" => Some((le_u24(24)? + 1, le_u24(27)? + 1)),
+                b"VP8L" => {
+                    let bits = le_u32(21)?;
+                    Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+                }
+                b"VP8 " => Some(((le_u16(26)? & 0x3FFF) as u32, (le_u16(28)? & 0x3FFF) as u32)),
+                _ => None,
+            },
+            MimeType::Jpeg => {
+                // Walk the segments until a start of frame marker.
+                let mut i = 2;
+                loop {
+                    if *buffer.get(i)? != 0xFF {
+                        return None;
+                    }
+                    let marker = *buffer.get(i + 1)?;
+                    if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                        return Some((be_u16(i + 7)? as u32, be_u16(i + 5)? as u32));
+                    }
+                    i += 2 + be_u16(i + 2)? as usize;
+                }
+            }
+            // The size of AVIF requires parsing the ISOBMFF boxes, SVG has no pixels.
+            MimeType::Avif | MimeType::Svg => None,
+        }
+    }
+
+    /// Converts the buffer into an image resvg can render.
+    ///
+    /// Formats resvg cannot decode by itself are converted to PNG.
+    /// The text of SVG images is converted with `fontdb`, like the text of the parent.
+    pub(crate) fn to_image_kind(
+        &self,
+        buffer: Arc<Vec<u8>>,
+        opts: &usvg::Options,
+        fontdb: &usvg::fontdb::Database,
+    ) -> Result<ImageKind, Error> {
+        Ok(match self {
+            MimeType::Png => ImageKind::PNG(buffer),
+            MimeType::Jpeg => ImageKind::JPEG(buffer),
+            MimeType::Gif => ImageKind::GIF(buffer),
+            MimeType::Webp | MimeType::Bmp => {
+                let format = match self {
+                    MimeType::Webp => image::ImageFormat::WebP,
+                    _ => image::ImageFormat::Bmp,
+                };
+                let image = image::load_from_memory_with_format(&buffer, format)
+                    .map_err(|e| Error::ImageDecoding(e.to_string()))?
+                    .to_rgba8();
+                ImageKind::PNG(Arc::new(encode_rgba_png(
+                    image.width(),
+                    image.height(),
+                    image.as_raw(),
+                )?))
+            }
+            MimeType::Ico => {
+                let icon = ico::IconDir::read(std::io::Cursor::new(buffer.as_slice()))
+                    .map_err(|e| Error::ImageDecoding(e.to_string()))?;
+                // Use the largest icon
+                let entry = icon
+                    .entries()
+                    .iter()
+                    .max_by_key(|entry| entry.width() * entry.height())
+                    .ok_or(Error::UnsupportedImage)?;
+                if entry.is_png() {
+                    ImageKind::PNG(Arc::new(entry.data().to_vec()))
+                } else {
+                    let image = entry
+                        .decode()
+                        .map_err(|e| Error::ImageDecoding(e.to_string()))?;
+                    ImageKind::PNG(Arc::new(encode_rgba_png(
+                        image.width(),
+                        image.height(),
+                        image.rgba_data(),
+                    )?))
+                }
+            }
+            MimeType::Avif => return Err(Error::UndecodableImage("AVIF")),
+            MimeType::Svg => {
+                // Same as usvg's nested SVG images: inherit the parent options, but do not load
+                // any images, which also prevents an SVG from referencing itself. Data URIs are
+                // skipped too, they would bypass the image limits of the parent.
+                let mut sub_opts = usvg::Options {
+                    resources_dir: None,
+                    dpi: opts.dpi,
+                    font_family: opts.font_family.clone(),
+                    font_size: opts.font_size,
+                    languages: opts.languages.clone(),
+                    shape_rendering: opts.shape_rendering,
+                    text_rendering: opts.text_rendering,
+                    image_rendering: opts.image_rendering,
+                    default_size: opts.default_size,
+                    image_href_resolver: usvg::ImageHrefResolver::default(),
+                };
+                sub_opts.image_href_resolver.resolve_data =
+                    Arc::new(|_: &str, _: Arc<Vec<u8>>, _: &usvg::Options| None);
+                sub_opts.image_href_resolver.resolve_string =
+                    Arc::new(|_: &str, _: &usvg::Options| None);
+                let mut tree = usvg::Tree::from_data(&buffer, &sub_opts)?;
+                tree.convert_text(fontdb);
+                ImageKind::SVG(tree)
+            }
+        })
+    }
+}
+
+/// Checks for an ISOBMFF `ftyp` box with an `avif` or `avis` major or compatible brand.
+fn is_avif(buffer: &[u8]) -> bool {
+    if buffer.get(4..8) != Some(&b"ftyp"[..]) {
+        return false;
+    }
+    let size = u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+    let ftyp = &buffer[..size.min(buffer.len())];
+    // The major brand, then the minor version, then the compatible brands.
+    let major = ftyp.get(8..12).into_iter();
+    let compatible = ftyp.get(16..).unwrap_or_default().chunks_exact(4);
+    major
+        .chain(compatible)
+        .any(|brand| brand == b"avif" || brand == b"avis")
+}
+
+/// Checks the start of an uncompressed SVG, skipping the BOM, whitespace and comments.
+fn is_svg_text(buffer: &[u8]) -> bool {
+    let mut text = buffer.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(buffer);
+    loop {
+        text = text.trim_ascii_start();
+        match text.strip_prefix(b"<!--") {
+            Some(comment) => match comment.windows(3).position(|w| w == b"-->") {
+                Some(end) => text = &comment[end + 3..],
+                None => return false,
+            },
+            None => {
+                return text.starts_with(b"<svg")
+                    || text.starts_with(b"<?xml")
+                    || text.starts_with(b"<!DOCTYPE svg");
+            }
+        }
+    }
+}
+
+fn encode_rgba_png(width: u32, height: u32, data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+    writer.finish()?;
+    Ok(png)
 }
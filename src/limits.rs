@@ -0,0 +1,267 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+
+use resvg::usvg;
+use roxmltree::{Document, Node, NodeId, ParsingOptions};
+
+use crate::error::Error;
+use crate::options::{JsLimitsOptions, is_remote_href};
+
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+/// The default of the `maxDecompressedBytes` limit.
+const DEFAULT_MAX_DECOMPRESSED_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Checks the gzip magic number.
+pub(crate) fn is_svgz(data: &[u8]) -> bool {
+    data.starts_with(&[0x1f, 0x8b])
+}
+
+/// Decompresses SVGZ data, other data is returned as is.
+///
+/// The decompressed size is limited, so that a small gzip bomb cannot exhaust the memory.
+pub(crate) fn decompress_svgz<'a>(
+    data: &'a [u8],
+    limits: &JsLimitsOptions,
+) -> Result<Cow<'a, [u8]>, Error> {
+    if !is_svgz(data) {
+        return Ok(Cow::Borrowed(data));
+    }
+    let max = limits
+        .max_decompressed_bytes
+        .unwrap_or(DEFAULT_MAX_DECOMPRESSED_BYTES);
+    let mut decompressed = Vec::with_capacity(data.len() * 2);
+    GzDecoder::new(data)
+        .take(max.saturating_add(1))
+        .read_to_end(&mut decompressed)
+        .map_err(|_| usvg::Error::MalformedGZip)?;
+    if decompressed.len() as u64 > max {
+        return Err(Error::DecompressedSizeLimit(max));
+    }
+    Ok(Cow::Owned(decompressed))
+}
+
+/// Checks the node count and `<use>` depth limits on the raw XML, before usvg expands `<use>`.
+///
+/// A `<use>` bomb only blows up during the usvg parse, so this is done on the roxmltree document.
+/// Documents roxmltree cannot parse are left to usvg to report.
+///
+/// With `collect_hrefs`, the http(s) hrefs of all `<image>` elements are returned, including the
+/// ones nested in patterns, masks and clip paths, so that the `imageResolver` can be called
+/// before usvg parses the document.
+pub(crate) fn check_document(
+    data: &[u8],
+    limits: &JsLimitsOptions,
+    collect_hrefs: bool,
+) -> Result<Vec<String>, Error> {
+    if !collect_hrefs && limits.max_nodes.is_none() && limits.max_use_depth.is_none() {
+        return Ok(vec![]);
+    }
+
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return Ok(vec![]),
+    };
+    let doc = match Document::parse_with_options(text, ParsingOptions { allow_dtd: true }) {
+        Ok(doc) => doc,
+        Err(_) => return Ok(vec![]),
+    };
+
+    let mut hrefs: Vec<String> = vec![];
+    if collect_hrefs {
+        for node in doc.descendants().filter(|n| n.tag_name().name() == "image") {
+            let href = node
+                .attribute((XLINK_NS, "href"))
+                .or_else(|| node.attribute("href"));
+            if let Some(href) = href.filter(|href| is_remote_href(href)) {
+                if !hrefs.iter().any(|h| h == href) {
+                    hrefs.push(href.to_string());
+                }
+            }
+        }
+    }
+    if limits.max_nodes.is_none() && limits.max_use_depth.is_none() {
+        return Ok(hrefs);
+    }
+
+    let mut scan = Scan {
+        limits,
+        ids: doc
+            .descendants()
+            .filter_map(|n| n.attribute("id").map(|id| (id, n)))
+            .collect(),
+        expanded: HashMap::new(),
+        visiting: HashSet::new(),
+    };
+    scan.run(doc.root_element())?;
+    Ok(hrefs)
+}
+
+struct Scan<'a, 'input> {
+    limits: &'a JsLimitsOptions,
+    ids: HashMap<&'a str, Node<'a, 'input>>,
+    /// The expanded node count and `<use>` depth of already visited elements.
+    expanded: HashMap<NodeId, (u64, u32)>,
+    /// The elements being visited, to skip recursive `<use>` like usvg does.
+    visiting: HashSet<NodeId>,
+}
+
+/// An element being visited by `Scan::run()`.
+struct Frame<'a, 'input> {
+    node: Node<'a, 'input>,
+    /// Whether the element is the target of a `<use>`.
+    via_use: bool,
+    /// The `<use>` depth from the root element to this element.
+    use_depth: u32,
+    /// The elements left to visit, popped from the end: the children, then the `<use>` target.
+    pending: Vec<(Node<'a, 'input>, bool)>,
+    /// The number of elements and the `<use>` depth below this element, visited so far.
+    nodes: u64,
+    depth: u32,
+}
+
+impl<'a, 'input> Scan<'a, 'input> {
+    /// Counts the elements once all `<use>` are expanded, and checks the `<use>` depth.
+    ///
+    /// An explicit stack is used, since a long `<use>` chain would overflow the native stack.
+    /// The `<use>` depth is checked as the elements are entered, so that such a chain
+    /// stops at `maxUseDepth`, and the node count as every subtree is left.
+    fn run(&mut self, root: Node<'a, 'input>) -> Result<(), Error> {
+        let mut stack = vec![self.enter(root, false, 0)];
+        while let Some(frame) = stack.last_mut() {
+            if let Some((child, via_use)) = frame.pending.pop() {
+                let use_depth = frame.use_depth + via_use as u32;
+                if let Some(&(nodes, depth)) = self.expanded.get(&child.id()) {
+                    self.check_use_depth(use_depth.saturating_add(depth))?;
+                    frame.add(nodes, depth + via_use as u32);
+                } else if !self.visiting.contains(&child.id()) {
+                    self.check_use_depth(use_depth)?;
+                    let child = self.enter(child, via_use, use_depth);
+                    stack.push(child);
+                }
+                continue;
+            }
+
+            let (id, nodes, depth) = (frame.node.id(), frame.nodes, frame.depth);
+            let depth_above = depth + frame.via_use as u32;
+            self.check_nodes(nodes)?;
+            self.visiting.remove(&id);
+            self.expanded.insert(id, (nodes, depth));
+            stack.pop();
+            if let Some(parent) = stack.last_mut() {
+                parent.add(nodes, depth_above);
+            }
+        }
+        Ok(())
+    }
+
+    fn enter(
+        &mut self,
+        node: Node<'a, 'input>,
+        via_use: bool,
+        use_depth: u32,
+    ) -> Frame<'a, 'input> {
+        self.visiting.insert(node.id());
+        let mut pending = vec![];
+        if node.tag_name().name() == "use" {
+            let target = node
+                .attribute((XLINK_NS, "href"))
+                .or_else(|| node.attribute("href"))
+                .and_then(|href| href.strip_prefix('#'))
+                .and_then(|id| self.ids.get(id).copied());
+            pending.extend(target.map(|target| (target, true)));
+        }
+        pending.extend(
+            node.children()
+                .rev()
+                .filter(|n| n.is_element())
+                .map(|n| (n, false)),
+        );
+        Frame {
+            node,
+            via_use,
+            use_depth,
+            pending,
+            nodes: 1,
+            depth: 0,
+        }
+    }
+
+    fn check_use_depth(&self, depth: u32) -> Result<(), Error> {
+        match self.limits.max_use_depth {
+            Some(max) if depth > max => Err(Error::UseDepthLimit(max)),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_nodes(&self, nodes: u64) -> Result<(), Error> {
+        match self.limits.max_nodes {
+            Some(max) if nodes > max => Err(Error::NodeCountLimit(max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Frame<'_, '_> {
+    fn add(&mut self, nodes: u64, depth: u32) {
+        self.nodes = self.nodes.saturating_add(nodes);
+        self.depth = self.depth.max(depth);
+    }
+}
+
+/// A point in time after which parsing or rendering is aborted.
+///
+/// `std::time::Instant` is not available on `wasm32-unknown-unknown`, so `Date.now()` is used there.
+#[derive(Clone, Copy)]
+pub(crate) struct Deadline {
+    timeout: u64,
+    // `None` when the timeout is too large to be represented.
+    #[cfg(not(target_arch = "wasm32"))]
+    end: Option<std::time::Instant>,
+    #[cfg(target_arch = "wasm32")]
+    end: f64,
+}
+
+impl Deadline {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn new(timeout: u64) -> Deadline {
+        use std::time::{Duration, Instant};
+        Deadline {
+            timeout,
+            end: Instant::now().checked_add(Duration::from_millis(timeout)),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn new(timeout: u64) -> Deadline {
+        Deadline {
+            timeout,
+            end: js_sys::Date::now() + timeout as f64,
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        if self.is_over() {
+            Err(Error::RenderTimeout(self.timeout))
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn is_over(&self) -> bool {
+        self.end.is_some_and(|end| std::time::Instant::now() > end)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn is_over(&self) -> bool {
+        js_sys::Date::now() > self.end
+    }
+}
@@ -0,0 +1,248 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::cell::{Cell, RefCell};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+use std::sync::Once;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+#[cfg(not(target_arch = "wasm32"))]
+use napi::{
+    Status,
+    bindgen_prelude::FnArgs,
+    threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use napi_derive::napi;
+use resvg::usvg;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::{JsValue, wasm_bindgen};
+
+use crate::error::Error;
+
+/// A warning logged while parsing or rendering the SVG.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+#[cfg_attr(not(target_arch = "wasm32"), napi(object))]
+#[derive(Clone, Debug)]
+pub struct Warning {
+    /// `warn` or `error`.
+    pub level: String,
+    /// The module that logged it, e.g. `usvg_parser::image`.
+    pub target: String,
+    pub message: String,
+    /// The id of the element the message refers to, when it mentions one.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = elementId))]
+    pub element_id: Option<String>,
+}
+
+impl Warning {
+    /// Finds an element id quoted in the message, e.g. `Mask 'mask1' has an invalid size.`
+    pub(crate) fn resolve_element_id(&mut self, tree: &usvg::Tree) {
+        self.element_id = self
+            .message
+            .split('\'')
+            .skip(1)
+            .step_by(2)
+            .map(|id| id.trim_start_matches('#'))
+            .find(|id| tree.node_by_id(id).is_some())
+            .map(|id| id.to_string());
+    }
+}
+
+thread_local! {
+    // The warnings of the instance being parsed or rendered on this thread.
+    static WARNINGS: RefCell<Option<Vec<Warning>>> = const { RefCell::new(None) };
+    // The `logLevel` of the instance being parsed or rendered on this thread.
+    static SCOPE_LEVEL: Cell<Option<LevelFilter>> = const { Cell::new(None) };
+}
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    static JS_LOGGER: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+}
+
+/// The callback of `setLogger()`, called with `(level, target, message)`.
+///
+/// Weak, so that a logger does not keep the process alive.
+#[cfg(not(target_arch = "wasm32"))]
+pub type JsLogger = ThreadsafeFunction<
+    FnArgs<(String, String, String)>,
+    (),
+    FnArgs<(String, String, String)>,
+    Status,
+    false,
+    true,
+>;
+
+#[cfg(not(target_arch = "wasm32"))]
+static JS_LOGGER: Mutex<Option<JsLogger>> = Mutex::new(None);
+
+/// The level set by `setLogLevel()`, used outside of an instance with its own `logLevel`.
+static LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Error as usize);
+
+fn global_level() -> LevelFilter {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+fn current_level() -> LevelFilter {
+    SCOPE_LEVEL.with(|l| l.get()).unwrap_or_else(global_level)
+}
+
+/// Lets `log` call the logger for `level`, warnings are always collected.
+fn raise_max_level(level: LevelFilter) {
+    let level = level.max(LevelFilter::Warn);
+    if level > log::max_level() {
+        log::set_max_level(level);
+    }
+}
+
+/// Collects the warnings logged on the current thread, until it is finished or dropped.
+///
+/// The instance's `logLevel` also applies until then.
+pub(crate) struct WarningCollector {
+    previous: Option<Vec<Warning>>,
+    previous_level: Option<LevelFilter>,
+}
+
+impl WarningCollector {
+    pub(crate) fn start(level: Option<LevelFilter>) -> WarningCollector {
+        init();
+        if let Some(level) = level {
+            raise_max_level(level);
+        }
+        WarningCollector {
+            previous: WARNINGS.with(|w| w.replace(Some(vec![]))),
+            previous_level: SCOPE_LEVEL.with(|l| l.replace(level)),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<Warning> {
+        WARNINGS.with(|w| w.take()).unwrap_or_default()
+    }
+}
+
+impl Drop for WarningCollector {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        WARNINGS.with(|w| *w.borrow_mut() = previous);
+        SCOPE_LEVEL.with(|l| l.set(self.previous_level));
+    }
+}
+
+struct Logger {
+    #[cfg(not(target_arch = "wasm32"))]
+    stderr: env_logger::Logger,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn || metadata.level() <= current_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() <= Level::Warn {
+            WARNINGS.with(|w| {
+                if let Ok(mut w) = w.try_borrow_mut() {
+                    if let Some(warnings) = w.as_mut() {
+                        warnings.push(Warning {
+                            level: record.level().as_str().to_lowercase(),
+                            target: record.target().to_string(),
+                            message: record.args().to_string(),
+                            element_id: None,
+                        });
+                    }
+                }
+            });
+        }
+        if record.level() > current_level() {
+            return;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Ok(logger) = JS_LOGGER.lock() {
+                if let Some(logger) = logger.as_ref() {
+                    let args = (
+                        record.level().as_str().to_lowercase(),
+                        record.target().to_string(),
+                        record.args().to_string(),
+                    );
+                    let _ = logger.call(args.into(), ThreadsafeFunctionCallMode::NonBlocking);
+                    return;
+                }
+            }
+            self.stderr.log(record);
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        JS_LOGGER.with(|logger| {
+            if let Some(logger) = logger.borrow().as_ref() {
+                let _ = logger.call3(
+                    &JsValue::NULL,
+                    &record.level().as_str().to_lowercase().into(),
+                    &record.target().into(),
+                    &record.args().to_string().into(),
+                );
+            }
+        });
+    }
+
+    fn flush(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.stderr.flush();
+    }
+}
+
+/// Installs the logger once.
+fn init() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let logger = Logger {
+            // Filtered by `current_level()`, `RUST_LOG` can still narrow it down.
+            #[cfg(not(target_arch = "wasm32"))]
+            stderr: env_logger::builder()
+                .filter_level(LevelFilter::Trace)
+                .build(),
+        };
+        if log::set_boxed_logger(Box::new(logger)).is_ok() {
+            raise_max_level(global_level());
+        }
+    });
+}
+
+/// Changes the log level used outside of an instance with its own `logLevel`.
+pub(crate) fn set_level(level: &str) -> Result<(), Error> {
+    let level: LevelFilter = level
+        .parse()
+        .map_err(|_| Error::InvalidLogLevel(level.to_string()))?;
+    init();
+    LEVEL.store(level as usize, Ordering::Relaxed);
+    log::set_max_level(level.max(LevelFilter::Warn));
+    Ok(())
+}
+
+/// Forwards the log records to a javascript callback instead of stderr, `None` restores stderr.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn set_js_logger(logger: Option<JsLogger>) {
+    init();
+    if let Ok(mut current) = JS_LOGGER.lock() {
+        *current = logger;
+    }
+}
+
+/// Forwards the log records to a javascript callback, `None` removes it.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn set_js_logger(logger: Option<js_sys::Function>) {
+    init();
+    JS_LOGGER.with(|current| *current.borrow_mut() = logger);
+}
@@ -2,21 +2,27 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use std::sync::Arc;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
 
+use crate::MimeType;
 use crate::error::Error;
+use crate::limits::Deadline;
 #[cfg(not(target_arch = "wasm32"))]
 use napi::{Either, bindgen_prelude::Buffer};
-use resvg::tiny_skia::{Pixmap, Transform};
-#[cfg(not(target_arch = "wasm32"))]
-use resvg::usvg::TreeParsing;
+use resvg::tiny_skia::{IntRect, Pixmap, Transform};
 use resvg::usvg::fontdb::Database;
 use resvg::usvg::{self, ImageHrefResolver, ImageKind, Options};
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, de::DeserializeOwned};
 
 /// Image fit options.
 /// This provides the deserializer for `usvg::FitTo`.
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 #[serde(
     tag = "mode",
     content = "value",
@@ -25,6 +31,7 @@ use serde::{Deserialize, Deserializer};
 )]
 pub enum FitToDef {
     /// Keep original size.
+    #[default]
     Original,
     /// Scale to width.
     Width(u32),
@@ -67,35 +74,100 @@ enum LogLevelDef {
     Trace,
 }
 
+fn deserialize_log_level<'de, D>(deserializer: D) -> Result<Option<log::LevelFilter>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    LogLevelDef::deserialize(deserializer).map(Some)
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) trait ResvgReadable {
-    fn load(&self, options: &usvg::Options) -> Result<usvg::Tree, usvg::Error>;
+    /// Reads the SVG data, decompressing SVGZ within the `maxDecompressedBytes` limit.
+    fn read(&self, limits: &JsLimitsOptions) -> Result<Cow<'_, [u8]>, Error>;
+
+    /// The directory relative image hrefs are resolved against, unless `resourcesDir` is set.
+    fn resources_dir(&self) -> Option<&Path> {
+        None
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 impl ResvgReadable for &str {
-    fn load(&self, options: &usvg::Options) -> Result<usvg::Tree, usvg::Error> {
-        usvg::Tree::from_str(self, options)
+    fn read(&self, _: &JsLimitsOptions) -> Result<Cow<'_, [u8]>, Error> {
+        Ok(Cow::Borrowed(self.as_bytes()))
     }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 impl ResvgReadable for &[u8] {
-    fn load(&self, options: &usvg::Options) -> Result<usvg::Tree, usvg::Error> {
-        usvg::Tree::from_data(self, options)
+    fn read(&self, limits: &JsLimitsOptions) -> Result<Cow<'_, [u8]>, Error> {
+        crate::limits::decompress_svgz(self, limits)
     }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-impl ResvgReadable for &Either<String, Buffer> {
-    fn load(&self, options: &usvg::Options) -> Result<usvg::Tree, usvg::Error> {
+impl ResvgReadable for Either<String, Buffer> {
+    fn read(&self, limits: &JsLimitsOptions) -> Result<Cow<'_, [u8]>, Error> {
         match self {
-            Either::A(s) => s.as_str().load(options),
-            Either::B(b) => b.as_ref().load(options),
+            Either::A(s) => Ok(Cow::Borrowed(s.as_bytes())),
+            Either::B(b) => crate::limits::decompress_svgz(b.as_ref(), limits),
         }
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+impl ResvgReadable for PathBuf {
+    fn read(&self, limits: &JsLimitsOptions) -> Result<Cow<'_, [u8]>, Error> {
+        let data =
+            std::fs::read(self).map_err(|e| Error::ReadFile(self.display().to_string(), e))?;
+        if crate::limits::is_svgz(&data) {
+            Ok(Cow::Owned(
+                crate::limits::decompress_svgz(&data, limits)?.into_owned(),
+            ))
+        } else {
+            Ok(Cow::Owned(data))
+        }
+    }
+
+    fn resources_dir(&self) -> Option<&Path> {
+        // `parent()` of a bare file name is an empty path.
+        match self.parent() {
+            Some(dir) if dir.as_os_str().is_empty() => Some(Path::new(".")),
+            dir => dir,
+        }
+    }
+}
+
+/// Parses the javascript options.
+///
+/// Invalid options are an error, unless `lenient: true` is set,
+/// in which case the default options are used instead.
+pub(crate) fn parse_options<T: DeserializeOwned + Default>(
+    options: Option<String>,
+) -> Result<T, Error> {
+    let mut value: serde_json::Value = match options {
+        Some(options) => serde_json::from_str(&options).map_err(Error::InvalidOptions)?,
+        None => return Ok(T::default()),
+    };
+    if value.is_null() {
+        return Ok(T::default());
+    }
+    let lenient = value
+        .as_object_mut()
+        .and_then(|o| o.remove("lenient"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    match serde_json::from_value(value) {
+        Ok(options) => Ok(options),
+        Err(e) if lenient => {
+            log::warn!("Invalid options, falling back to the defaults: {e}");
+            Ok(T::default())
+        }
+        Err(e) => Err(Error::InvalidOptions(e)),
+    }
+}
+
 /// The javascript options passed to `render()`.
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase", default, deny_unknown_fields)]
@@ -161,8 +233,32 @@ pub struct JsOptions {
     /// Crop options
     pub crop: JsCropOptions,
 
-    #[serde(with = "LogLevelDef")]
-    pub log_level: log::LevelFilter,
+    /// Trims the transparent pixels around the content, after `crop`.
+    ///
+    /// Default: `None`
+    pub trim: Option<JsTrimOptions>,
+
+    /// The directory used to resolve relative and `file://` image hrefs.
+    ///
+    /// Default: `None`
+    pub resources_dir: Option<String>,
+
+    /// Resource limits for untrusted SVGs.
+    pub limits: JsLimitsOptions,
+
+    /// Refuse local images whose real path is outside of `resources_dir`,
+    /// e.g. `../secret.png` or a symlink pointing elsewhere.
+    ///
+    /// Only takes effect when `resources_dir` is set.
+    ///
+    /// Default: true
+    pub resources_sandbox: bool,
+
+    /// The log level while parsing and rendering this SVG.
+    ///
+    /// Default: the level set by `setLogLevel()`, `error` unless changed
+    #[serde(deserialize_with = "deserialize_log_level")]
+    pub log_level: Option<log::LevelFilter>,
 }
 
 impl Default for JsOptions {
@@ -177,7 +273,11 @@ impl Default for JsOptions {
             fit_to: FitToDef::Original,
             background: None,
             crop: JsCropOptions::default(),
-            log_level: log::LevelFilter::Error,
+            trim: None,
+            resources_dir: None,
+            resources_sandbox: true,
+            limits: JsLimitsOptions::default(),
+            log_level: None,
         }
     }
 }
@@ -190,9 +290,13 @@ impl JsOptions {
         #[cfg(target_arch = "wasm32")]
         let fontdb = Database::new();
 
-        // Build the SVG options
-        let opts = usvg::Options {
-            resources_dir: None,
+        (self.usvg_options(), fontdb)
+    }
+
+    /// Builds the SVG options, without loading the fonts.
+    pub(crate) fn usvg_options(&self) -> usvg::Options {
+        usvg::Options {
+            resources_dir: self.resources_dir.as_ref().map(PathBuf::from),
             dpi: self.dpi,
             font_family: self.font.default_font_family.clone(),
             font_size: self.font.default_font_size,
@@ -202,8 +306,7 @@ impl JsOptions {
             image_rendering: self.image_rendering,
             default_size: usvg::Size::from_wh(100.0, 100.0).unwrap(),
             image_href_resolver: usvg::ImageHrefResolver::default(),
-        };
-        (opts, fontdb)
+        }
     }
 
     pub(crate) fn create_pixmap(&self, width: u32, height: u32) -> Result<Pixmap, Error> {
@@ -297,6 +400,269 @@ impl Default for JsFontOptions {
     }
 }
 
+/// The resource limits passed to `render()`.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsLimitsOptions {
+    /// The maximum width * height of a single image, read from its header before decoding.
+    ///
+    /// Default: None
+    pub max_image_pixels: Option<u64>,
+
+    /// The maximum encoded size of a single image in bytes.
+    ///
+    /// Default: None
+    pub max_image_bytes: Option<u64>,
+
+    /// The maximum number of images in the SVG.
+    ///
+    /// Default: None
+    pub max_images: Option<u32>,
+
+    /// Skip images over the limits instead of failing.
+    ///
+    /// Default: false
+    pub skip_oversized_images: bool,
+
+    /// The maximum width of the rendered image, after `fitTo` is applied.
+    ///
+    /// Default: None
+    pub max_width: Option<u32>,
+
+    /// The maximum height of the rendered image, after `fitTo` is applied.
+    ///
+    /// Default: None
+    pub max_height: Option<u32>,
+
+    /// The maximum width * height of the rendered image, after `fitTo` is applied.
+    ///
+    /// Default: None
+    pub max_pixels: Option<u64>,
+
+    /// The maximum number of elements, counted with every `<use>` expanded.
+    ///
+    /// Default: None
+    pub max_nodes: Option<u64>,
+
+    /// The maximum nesting depth of `<use>` references.
+    ///
+    /// Default: None
+    pub max_use_depth: Option<u32>,
+
+    /// The maximum size in bytes of a decompressed SVGZ input.
+    ///
+    /// Default: 100 MiB
+    pub max_decompressed_bytes: Option<u64>,
+
+    /// The maximum time in milliseconds spent parsing, and then again rendering.
+    ///
+    /// Parsing is checked between its steps and before every image is loaded. Rendering is
+    /// checked between elements, so a single heavy element can overrun it, as well as a group
+    /// with opacity, a clip path, a mask or filters, which is rendered as a whole.
+    ///
+    /// Default: None
+    pub render_timeout: Option<u64>,
+}
+
+impl JsLimitsOptions {
+    /// Checks the size limits of a single image, without decoding it.
+    pub(crate) fn check_image(
+        &self,
+        href: &str,
+        mime: Option<&MimeType>,
+        buffer: &[u8],
+    ) -> Result<(), Error> {
+        if let Some(max) = self.max_image_bytes {
+            if buffer.len() as u64 > max {
+                return Err(Error::ImageBytesLimit(href.to_string(), buffer.len(), max));
+            }
+        }
+        if let (Some(max), Some((width, height))) = (
+            self.max_image_pixels,
+            mime.and_then(|mime| mime.dimensions(buffer)),
+        ) {
+            if width as u64 * height as u64 > max {
+                return Err(Error::ImagePixelsLimit(
+                    href.to_string(),
+                    width,
+                    height,
+                    max,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the size limits of the rendered image.
+    pub(crate) fn check_output(&self, width: u32, height: u32) -> Result<(), Error> {
+        if let Some(max) = self.max_width {
+            if width > max {
+                return Err(Error::WidthLimit(width, max));
+            }
+        }
+        if let Some(max) = self.max_height {
+            if height > max {
+                return Err(Error::HeightLimit(height, max));
+            }
+        }
+        if let Some(max) = self.max_pixels {
+            if width as u64 * height as u64 > max {
+                return Err(Error::PixelsLimit(width, height, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The javascript options passed to `measureText()`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsMeasureTextOptions {
+    /// Font related options, used to build the font database.
+    pub font: JsFontOptions,
+
+    /// A CSS-like list of font families, e.g. `"Pacifico, serif"`.
+    ///
+    /// Default: the default font family
+    pub font_family: Option<String>,
+
+    /// The font size.
+    ///
+    /// Default: the default font size
+    pub font_size: Option<f32>,
+
+    /// The font weight.
+    ///
+    /// Default: 400
+    pub weight: u16,
+
+    /// Extra space added after each character.
+    ///
+    /// Default: 0
+    pub letter_spacing: f32,
+}
+
+impl Default for JsMeasureTextOptions {
+    fn default() -> JsMeasureTextOptions {
+        JsMeasureTextOptions {
+            font: JsFontOptions::default(),
+            font_family: None,
+            font_size: None,
+            weight: 400,
+            letter_spacing: 0.0,
+        }
+    }
+}
+
+/// The javascript options passed to `renderElement()`.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsRenderElementOptions {
+    /// Scales the element's bounding box, including the padding.
+    ///
+    /// Default: original
+    pub fit_to: FitToDef,
+
+    /// Extra space around the bounding box, in user units.
+    ///
+    /// Default: 0
+    pub padding: f32,
+}
+
+/// The javascript options passed to `renderRegion()`.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsRenderRegionOptions {
+    /// The number of pixels per user unit.
+    ///
+    /// Default: 1, unless `output_width` is set
+    pub scale: Option<f64>,
+
+    /// The width of the image in pixels, the height follows the aspect ratio of the region.
+    ///
+    /// Default: None
+    pub output_width: Option<u32>,
+}
+
+impl JsRenderRegionOptions {
+    /// Resolves the size of the image and the transform mapping the `x, y, width, height`
+    /// region of user space onto it.
+    pub(crate) fn resolve(
+        &self,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    ) -> Result<(u32, u32, Transform), Error> {
+        let finite = [x, y, width, height].iter().all(|v| v.is_finite());
+        if !finite || width <= 0.0 || height <= 0.0 {
+            return Err(Error::InvalidRegion(format!(
+                "{x},{y},{width},{height} is empty or not finite"
+            )));
+        }
+        let scale = match (self.scale, self.output_width) {
+            (Some(_), Some(_)) => {
+                return Err(Error::InvalidRegion(
+                    "scale and outputWidth cannot be combined".to_string(),
+                ));
+            }
+            (Some(scale), None) if !(scale.is_finite() && scale > 0.0) => {
+                return Err(Error::InvalidRegion(format!(
+                    "scale {scale} must be a positive number"
+                )));
+            }
+            (Some(scale), None) => scale,
+            (None, Some(output_width)) => output_width as f64 / width,
+            (None, None) => 1.0,
+        };
+        let output_width = (width * scale).round();
+        let output_height = (height * scale).round();
+        if output_width < 1.0 || output_height < 1.0 {
+            return Err(Error::ZeroSized);
+        }
+        if output_width > u32::MAX as f64 || output_height > u32::MAX as f64 {
+            return Err(Error::AllocationFailed);
+        }
+        // Like `fitTo`, the region fills the rounded size exactly.
+        let sx = output_width / width;
+        let sy = output_height / height;
+        let transform = Transform::from_row(
+            sx as f32,
+            0.0,
+            0.0,
+            sy as f32,
+            (-x * sx) as f32,
+            (-y * sy) as f32,
+        );
+        Ok((output_width as u32, output_height as u32, transform))
+    }
+}
+
+/// The javascript options passed to `getElementBBox()` and `elementBBoxes()`.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsElementBBoxOptions {
+    /// What the bounding box includes.
+    ///
+    /// Default: fill, or visual for `innerBBox()`
+    pub kind: Option<BBoxKind>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BBoxKind {
+    /// The geometry of the shapes, like `SVGGraphicsElement.getBBox()`.
+    #[default]
+    Fill,
+    /// The geometry of the shapes, including their strokes.
+    Stroke,
+    /// The painted area, clipped by the clip paths, masks and the canvas.
+    Visual,
+    /// Like `Visual`, but with the exact stroke outline, including its joins, miter limit and
+    /// dashes, and the filter regions, e.g. a drop shadow.
+    Ink,
+}
+
 /// The font options passed to `load_fonts()`.
 #[derive(Deserialize, Default)]
 #[serde(rename_all = "camelCase", default, deny_unknown_fields)]
@@ -304,22 +670,217 @@ pub struct JsCropOptions {
     /// The rectangle's left x-axis coordinate.
     ///
     /// Default: 0
-    pub left: i32,
+    pub left: CropEdge,
 
     /// The rectangle's top y-axis coordinate.
     ///
     /// Default: 0
-    pub top: i32,
+    pub top: CropEdge,
 
     /// The rectangle's right x-axis coordinate. `None` targets the svg width.
     ///
     /// Default: None
-    pub right: Option<i32>,
+    pub right: Option<CropEdge>,
 
     /// The rectangle's bottom y-axis coordinate. `None` targets the svg height.
     ///
     /// Default: None
-    pub bottom: Option<i32>,
+    pub bottom: Option<CropEdge>,
+
+    /// The unit of the numeric coordinates.
+    ///
+    /// Default: pixels
+    pub units: CropUnits,
+}
+
+/// A coordinate of the `crop` option.
+#[derive(Clone, Copy)]
+pub enum CropEdge {
+    Value(f64),
+    /// A percentage of the output width or height.
+    Percent(f64),
+}
+
+impl Default for CropEdge {
+    fn default() -> Self {
+        CropEdge::Value(0.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for CropEdge {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum CropEdgeDef {
+            Number(f64),
+            String(String),
+        }
+        match CropEdgeDef::deserialize(deserializer)? {
+            CropEdgeDef::Number(value) => Ok(CropEdge::Value(value)),
+            CropEdgeDef::String(s) => s
+                .strip_suffix('%')
+                .and_then(|value| value.trim().parse().ok())
+                .map(CropEdge::Percent)
+                .ok_or_else(|| {
+                    serde::de::Error::custom(format_args!(
+                        "Invalid crop value '{s}', expected a number or a percentage like '25%'"
+                    ))
+                }),
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CropUnits {
+    /// The pixels of the rendered image.
+    #[default]
+    Pixels,
+    /// The SVG's user space, fractional values are rounded to the nearest pixel.
+    User,
+}
+
+impl JsCropOptions {
+    /// Resolves the rectangle in the pixels of a `width` x `height` image.
+    ///
+    /// `transform` maps user space to these pixels.
+    pub(crate) fn resolve(
+        &self,
+        width: u32,
+        height: u32,
+        transform: Transform,
+    ) -> Result<IntRect, Error> {
+        let resolve = |edge: CropEdge, size: u32, scale: f32, translate: f32| match edge {
+            CropEdge::Value(value) if self.units == CropUnits::User => {
+                value * scale as f64 + translate as f64
+            }
+            CropEdge::Value(value) => value,
+            CropEdge::Percent(percent) => percent / 100.0 * size as f64,
+        };
+        let (sx, tx, sy, ty) = (transform.sx, transform.tx, transform.sy, transform.ty);
+        let left = resolve(self.left, width, sx, tx).round();
+        let top = resolve(self.top, height, sy, ty).round();
+        let right = self
+            .right
+            .map_or(width as f64, |edge| resolve(edge, width, sx, tx).round());
+        let bottom = self
+            .bottom
+            .map_or(height as f64, |edge| resolve(edge, height, sy, ty).round());
+
+        // Also rejects NaN
+        let valid = (0.0..right).contains(&left)
+            && right <= width as f64
+            && (0.0..bottom).contains(&top)
+            && bottom <= height as f64;
+        if !valid {
+            return Err(Error::InvalidCrop(format!(
+                "{left},{top},{right},{bottom} is empty or outside of the {width}x{height} image"
+            )));
+        }
+        IntRect::from_ltrb(left as i32, top as i32, right as i32, bottom as i32)
+            .ok_or_else(|| Error::InvalidCrop(format!("{left},{top},{right},{bottom} is empty")))
+    }
+}
+
+/// The format of the images returned to javascript.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    /// A `RenderedImage`.
+    #[default]
+    Rgba,
+    /// A PNG encoded buffer.
+    Png,
+}
+
+/// The javascript options passed to `cropByBBox()`.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsCropByBBoxOptions {
+    /// The bleed area around the crop box in output pixels, on every side or per side.
+    ///
+    /// Default: 0
+    pub padding: JsCropPadding,
+
+    /// Makes the crop area square using the larger dimension.
+    ///
+    /// Default: false
+    pub square: bool,
+
+    /// Grows the crop area to this width / height ratio, `square` is a ratio of 1.
+    ///
+    /// Default: None
+    pub aspect_ratio: Option<f64>,
+
+    /// Positions the bounding box relative to the top-left corner of the current crop.
+    ///
+    /// Default: false
+    pub relative: bool,
+
+    /// Moves the content so that the cropped viewbox starts at 0,0, e.g. for `toString()`.
+    ///
+    /// Default: false
+    pub normalize: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum JsCropPadding {
+    Uniform(f64),
+    Sides(JsPaddingSides),
+}
+
+impl Default for JsCropPadding {
+    fn default() -> Self {
+        JsCropPadding::Uniform(0.0)
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct JsPaddingSides {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl JsCropPadding {
+    /// The padding as `[top, right, bottom, left]`.
+    pub(crate) fn sides(&self) -> [f32; 4] {
+        // Reject NaN, Infinity, and negative values
+        let valid = |val: f64| {
+            if val.is_finite() && val >= 0.0 {
+                val as f32
+            } else {
+                0.0
+            }
+        };
+        match self {
+            JsCropPadding::Uniform(val) => [valid(*val); 4],
+            JsCropPadding::Sides(s) => {
+                [valid(s.top), valid(s.right), valid(s.bottom), valid(s.left)]
+            }
+        }
+    }
+}
+
+/// The javascript `trim` option.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsTrimOptions {
+    /// Pixels with an alpha at or below it are trimmed.
+    ///
+    /// Default: 0
+    pub threshold: u8,
+
+    /// The transparent pixels kept around the content.
+    ///
+    /// Default: 0
+    pub padding: u32,
 }
 
 /// Deserializes `usvg::ShapeRendering`
@@ -366,14 +927,239 @@ where
     }
 }
 
-pub(crate) fn tweak_usvg_options(opts: &mut usvg::Options) {
+/// Image buffers returned by an image resolver, keyed by href.
+pub(crate) type ResolvedImages = HashMap<String, Arc<Vec<u8>>>;
+
+pub(crate) fn is_remote_href(href: &str) -> bool {
+    href.starts_with("https://") || href.starts_with("http://")
+}
+
+/// Decodes the `%XX` escapes of a `file://` URL path.
+fn percent_decode(path: &str) -> Option<String> {
+    let hex = |byte: Option<&u8>| byte.and_then(|&b| (b as char).to_digit(16));
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], hex(bytes.get(i + 1)), hex(bytes.get(i + 2))) {
+            (b'%', Some(high), Some(low)) => {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+            }
+            (byte, ..) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    match String::from_utf8(decoded) {
+        Ok(decoded) => Some(decoded),
+        Err(_) => {
+            log::warn!("'file://{path}' is not a valid UTF-8 path. Skipped.");
+            None
+        }
+    }
+}
+
+/// Resolves `href` against `dir`, refusing paths that escape it.
+fn sandboxed_path(dir: &Path, href: &str) -> Option<PathBuf> {
+    let root = dir.canonicalize().ok()?;
+    // `canonicalize()` follows symlinks and `..`, so only the real location is checked.
+    let path = root.join(href).canonicalize().ok()?;
+    if path.starts_with(&root) {
+        Some(path)
+    } else {
+        log::warn!("'{href}' is outside of the resources directory. Skipped.");
+        None
+    }
+}
+
+/// Enforces the image limits while parsing.
+///
+/// usvg resolvers cannot return errors, so the first violation is kept and reported by `finish()`.
+#[derive(Clone)]
+pub(crate) struct ImageGuard {
+    limits: JsLimitsOptions,
+    // The `renderTimeout` of the parse, usvg cannot be interrupted otherwise.
+    deadline: Option<Deadline>,
+    count: Arc<AtomicU32>,
+    error: Arc<Mutex<Option<Error>>>,
+}
+
+impl ImageGuard {
+    fn new(limits: JsLimitsOptions, deadline: Option<Deadline>) -> Self {
+        ImageGuard {
+            limits,
+            deadline,
+            count: Arc::new(AtomicU32::new(0)),
+            error: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Checks the deadline, once it is over no more images are loaded.
+    fn in_time(&self) -> bool {
+        match self.deadline.map(|deadline| deadline.check()) {
+            Some(Err(e)) => {
+                if let Ok(mut error) = self.error.lock() {
+                    error.get_or_insert(e);
+                }
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Counts one more image, then checks its limits.
+    fn admit(&self, href: &str, mime: Option<&MimeType>, buffer: &[u8]) -> bool {
+        if !self.in_time() {
+            return false;
+        }
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        let result = match self.limits.max_images {
+            Some(max) if count > max => Err(Error::ImageCountLimit(max)),
+            _ => self.limits.check_image(href, mime, buffer),
+        };
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                self.reject(e);
+                false
+            }
+        }
+    }
+
+    /// Skips an image over the limits, the first violation fails the parse unless
+    /// `skipOversizedImages` is set.
+    fn reject(&self, e: Error) {
+        log::warn!("{e}. Skipped.");
+        if !self.limits.skip_oversized_images {
+            if let Ok(mut error) = self.error.lock() {
+                error.get_or_insert(e);
+            }
+        }
+    }
+
+    /// Reads a local image, without reading more than `maxImageBytes` into memory.
+    fn read_file(&self, href: &str, path: &Path) -> Option<Vec<u8>> {
+        if !self.in_time() {
+            return None;
+        }
+        let max = self.limits.max_image_bytes;
+        let result = File::open(path).and_then(|file| {
+            let len = file.metadata()?.len();
+            if max.is_some_and(|max| len > max) {
+                return Ok(Err(len));
+            }
+            // The file can grow after its size was read, `admit()` then rejects it.
+            let mut buffer = Vec::with_capacity(len as usize);
+            file.take(max.map_or(u64::MAX, |max| max.saturating_add(1)))
+                .read_to_end(&mut buffer)?;
+            Ok(Ok(buffer))
+        });
+        match result {
+            Ok(Ok(buffer)) => Some(buffer),
+            Ok(Err(len)) => {
+                self.count.fetch_add(1, Ordering::Relaxed);
+                let max = max.unwrap_or_default();
+                self.reject(Error::ImageBytesLimit(href.to_string(), len as usize, max));
+                None
+            }
+            Err(e) => {
+                log::warn!("Failed to load '{href}' cause {e}.");
+                None
+            }
+        }
+    }
+
+    fn load(
+        &self,
+        href: &str,
+        buffer: Arc<Vec<u8>>,
+        opts: &Options,
+        fontdb: &Database,
+    ) -> Option<ImageKind> {
+        let kind = MimeType::parse(&buffer).and_then(|mime| {
+            if !self.admit(href, Some(&mime), &buffer) {
+                return Ok(None);
+            }
+            mime.to_image_kind(buffer.clone(), opts, fontdb).map(Some)
+        });
+        match kind {
+            Ok(kind) => kind,
+            Err(e) => {
+                log::warn!("Failed to load '{href}' cause {e}.");
+                None
+            }
+        }
+    }
+
+    /// Returns the first limit violation, unless oversized images are skipped, or the timeout.
+    pub(crate) fn finish(&self) -> Result<(), Error> {
+        match self.error.lock().ok().and_then(|mut error| error.take()) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn tweak_usvg_options(
+    opts: &mut usvg::Options,
+    limits: JsLimitsOptions,
+    sandbox: bool,
+    resolved: Arc<ResolvedImages>,
+    fontdb: Arc<Database>,
+    deadline: Option<Deadline>,
+) -> ImageGuard {
+    let guard = ImageGuard::new(limits, deadline);
     opts.image_href_resolver = ImageHrefResolver::default();
+
+    let data_guard = guard.clone();
+    opts.image_href_resolver.resolve_data =
+        Arc::new(move |mime: &str, data: Arc<Vec<u8>>, opts: &Options| {
+            let sniffed = MimeType::parse(&data).ok();
+            if !data_guard.admit(&format!("data:{mime}"), sniffed.as_ref(), &data) {
+                return None;
+            }
+            let resolver = ImageHrefResolver::default_data_resolver();
+            (resolver)(mime, data, opts)
+        });
+
+    let string_guard = guard.clone();
     opts.image_href_resolver.resolve_string = Arc::new(move |data: &str, opts: &Options| {
-        if data.starts_with("https://") || data.starts_with("http://") {
+        if is_remote_href(data) {
+            if let Some(buffer) = resolved.get(data) {
+                return string_guard.load(data, buffer.clone(), opts, &fontdb);
+            }
+            // Keep a placeholder so that it can still be resolved by `resolveImage()`.
             Some(ImageKind::RAW(1, 1, Arc::new(data.as_bytes().to_vec())))
         } else {
-            let resolver = ImageHrefResolver::default().resolve_string;
-            (resolver)(data, opts)
+            let href = match data.strip_prefix("file://") {
+                Some(path) => Cow::Owned(percent_decode(path)?),
+                None => Cow::Borrowed(data),
+            };
+            let path = match &opts.resources_dir {
+                Some(dir) if sandbox => sandboxed_path(dir, &href)?,
+                Some(dir) => dir.join(&*href),
+                None => PathBuf::from(&*href),
+            };
+            let buffer = string_guard.read_file(data, &path)?;
+            string_guard.load(data, Arc::new(buffer), opts, &fontdb)
         }
     });
+    guard
+}
+
+/// Adds a buffer returned by the `imageResolver`, failing on data that is not a supported image.
+pub(crate) fn add_resolved_image(
+    resolved: &mut ResolvedImages,
+    href: String,
+    buffer: Vec<u8>,
+) -> Result<(), Error> {
+    match MimeType::parse(&buffer) {
+        Err(_) => return Err(Error::UnsupportedResolvedImage(href)),
+        Ok(MimeType::Avif) => return Err(Error::UndecodableImage("AVIF")),
+        Ok(_) => {}
+    }
+    resolved.insert(href, Arc::new(buffer));
+    Ok(())
 }
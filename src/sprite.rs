@@ -0,0 +1,285 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::borrow::Cow;
+
+#[cfg(not(target_arch = "wasm32"))]
+use napi_derive::napi;
+use resvg::tiny_skia::Pixmap;
+use resvg::usvg::{self, NodeExt, NodeKind, TreeTextToPath};
+use roxmltree::{Document, ParsingOptions};
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::options::{FitToDef, ImageFormat};
+
+/// The javascript options passed to `renderSymbols()`.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsRenderSymbolsOptions {
+    /// Scales every symbol.
+    ///
+    /// Default: original
+    pub fit_to: FitToDef,
+
+    /// The format of the images.
+    ///
+    /// Default: rgba
+    pub format: ImageFormat,
+}
+
+/// The javascript options passed to `packSpriteSheet()`.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsPackOptions {
+    /// The space between two frames, in pixels.
+    ///
+    /// Default: 0
+    pub padding: u32,
+
+    /// The maximum width of the sprite sheet, frames wrap to a new row past it.
+    /// A frame wider than this gets a row of its own.
+    ///
+    /// Default: about the square root of the total area
+    pub max_width: Option<u32>,
+}
+
+/// The position of an SVG in the sprite sheet.
+#[cfg_attr(not(target_arch = "wasm32"), napi(object))]
+#[derive(Clone, Debug)]
+pub struct SpriteFrame {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A `<use>` appended to the document, so that usvg converts the `<symbol>` it references.
+pub(crate) struct SymbolUse {
+    id: String,
+    use_id: String,
+    size: Option<(f32, f32)>,
+}
+
+/// A converted `<symbol>`, detached from the tree.
+pub(crate) struct Symbol {
+    pub(crate) id: String,
+    node: usvg::Node,
+    // The symbol's viewBox size, otherwise its bounding box is used.
+    frame: Option<usvg::NonZeroRect>,
+}
+
+impl Symbol {
+    /// Builds a tree that renders only this symbol.
+    pub(crate) fn tree(&self) -> Option<usvg::Tree> {
+        let rect = match self.frame {
+            Some(rect) => rect,
+            None => {
+                let bbox = self.node.calculate_bbox()?;
+                usvg::NonZeroRect::from_xywh(bbox.x(), bbox.y(), bbox.width(), bbox.height())?
+            }
+        };
+        let root = usvg::Node::new(NodeKind::Group(usvg::Group::default()));
+        root.append(self.node.make_deep_copy());
+        Some(usvg::Tree {
+            size: rect.size(),
+            view_box: usvg::ViewBox {
+                rect,
+                aspect: usvg::AspectRatio::default(),
+            },
+            root,
+        })
+    }
+
+    /// Builds a tree that renders only this symbol, cropped to its bounding box like an element.
+    pub(crate) fn element_tree(&self, padding: f32) -> Result<usvg::Tree, Error> {
+        let root = usvg::Node::new(NodeKind::Group(usvg::Group::default()));
+        let node = self.node.make_deep_copy();
+        root.append(node.clone());
+        crate::element::crop_to_bbox(root, &node, &self.id, padding)
+    }
+}
+
+/// Checks whether the document may contain `<symbol>` elements, without parsing it.
+pub(crate) fn has_symbols(data: &[u8]) -> bool {
+    data.windows(7).any(|w| w == b"<symbol")
+}
+
+/// Appends a `<use>` for every `<symbol>` with an id to the root element.
+///
+/// usvg only converts the symbols that are referenced, the appended elements are removed
+/// from the tree again by `take_symbols()`. This is only done on a copy of the document
+/// parsed for the symbols, so that the rendered tree and its limits are not affected.
+pub(crate) fn inject_symbols(data: Cow<'_, [u8]>) -> (Cow<'_, [u8]>, Vec<SymbolUse>) {
+    let (insert_at, uses) = match find_symbols(&data) {
+        Some((insert_at, uses)) if !uses.is_empty() => (insert_at, uses),
+        _ => return (data, vec![]),
+    };
+
+    let mut injected = String::new();
+    for symbol in &uses {
+        injected.push_str(&format!(
+            r##"<use id="{}" href="#{}""##,
+            escape(&symbol.use_id),
+            escape(&symbol.id)
+        ));
+        if let Some((width, height)) = symbol.size {
+            injected.push_str(&format!(r#" width="{width}" height="{height}""#));
+        }
+        injected.push_str("/>");
+    }
+    let mut data = data.into_owned();
+    data.splice(insert_at..insert_at, injected.into_bytes());
+    (Cow::Owned(data), uses)
+}
+
+/// Finds the symbols with an id, and where the root element's end tag starts.
+fn find_symbols(data: &[u8]) -> Option<(usize, Vec<SymbolUse>)> {
+    // Most documents have no symbols, so avoid parsing them twice.
+    if !has_symbols(data) {
+        return None;
+    }
+    let text = std::str::from_utf8(data).ok()?;
+    let doc = Document::parse_with_options(text, ParsingOptions { allow_dtd: true }).ok()?;
+    let root = doc.root_element();
+    let insert_at = text[..root.range().end].rfind("</")?;
+
+    let uses = root
+        .descendants()
+        .filter(|n| n.has_tag_name("symbol"))
+        .filter_map(|n| Some((n.attribute("id").filter(|id| !id.is_empty())?, n)))
+        .enumerate()
+        .map(|(i, (id, node))| SymbolUse {
+            id: id.to_string(),
+            use_id: format!("__resvg_symbol_{i}"),
+            size: node.attribute("viewBox").and_then(view_box_size),
+        })
+        .collect();
+    Some((insert_at, uses))
+}
+
+fn view_box_size(view_box: &str) -> Option<(f32, f32)> {
+    let values: Vec<f32> = view_box
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse().ok())
+        .collect::<Option<_>>()?;
+    match values[..] {
+        [_, _, width, height] if width > 0.0 && height > 0.0 => Some((width, height)),
+        _ => None,
+    }
+}
+
+/// Escapes a value for an attribute or the text content of an element.
+pub(crate) fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+}
+
+/// Removes the appended `<use>` elements from the tree, converting their text to paths.
+pub(crate) fn take_symbols(
+    tree: &usvg::Tree,
+    uses: Vec<SymbolUse>,
+    fontdb: &usvg::fontdb::Database,
+) -> Vec<Symbol> {
+    if uses.is_empty() {
+        return vec![];
+    }
+    let mut symbols_tree = usvg::Tree {
+        size: tree.size,
+        view_box: tree.view_box,
+        root: usvg::Node::new(NodeKind::Group(usvg::Group::default())),
+    };
+    let mut symbols = vec![];
+    for symbol in uses {
+        // Empty symbols are not converted at all.
+        let node = match tree.node_by_id(&symbol.use_id) {
+            Some(node) => node,
+            None => continue,
+        };
+        node.detach();
+        symbols_tree.root.append(node.clone());
+        symbols.push(Symbol {
+            id: symbol.id,
+            node,
+            frame: symbol
+                .size
+                .and_then(|(width, height)| usvg::NonZeroRect::from_xywh(0.0, 0.0, width, height)),
+        });
+    }
+    symbols_tree.convert_text(fontdb);
+    for symbol in &symbols {
+        symbol.node.detach();
+    }
+    symbols
+}
+
+/// Packs the images into rows, tallest first, returning the sprite sheet size and the frames
+/// in the order of `images`.
+pub(crate) fn pack(
+    images: &[Pixmap],
+    padding: u32,
+    max_width: Option<u32>,
+) -> (u32, u32, Vec<SpriteFrame>) {
+    let widest = images.iter().map(|i| i.width()).max().unwrap_or(0);
+    let max_width = max_width
+        .unwrap_or_else(|| {
+            let area: f64 = images
+                .iter()
+                .map(|i| (i.width() + padding) as f64 * (i.height() + padding) as f64)
+                .sum();
+            area.sqrt().ceil() as u32
+        })
+        .max(widest);
+
+    let mut order: Vec<usize> = (0..images.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(images[i].height()));
+
+    let mut frames = vec![None; images.len()];
+    let (mut x, mut y, mut row_height, mut width) = (0, 0, 0, 0);
+    for i in order {
+        let image = &images[i];
+        if x > 0 && x + image.width() > max_width {
+            y += row_height + padding;
+            x = 0;
+            row_height = 0;
+        }
+        frames[i] = Some(SpriteFrame {
+            x,
+            y,
+            width: image.width(),
+            height: image.height(),
+        });
+        width = width.max(x + image.width());
+        row_height = row_height.max(image.height());
+        x += image.width() + padding;
+    }
+    (
+        width,
+        y + row_height,
+        frames.into_iter().flatten().collect(),
+    )
+}
+
+/// Copies the images into a transparent sprite sheet.
+pub(crate) fn draw(
+    width: u32,
+    height: u32,
+    images: &[Pixmap],
+    frames: &[SpriteFrame],
+) -> Result<Pixmap, Error> {
+    let mut sheet = Pixmap::new(width, height).ok_or(Error::AllocationFailed)?;
+    let stride = width as usize * 4;
+    let data = sheet.data_mut();
+    for (image, frame) in images.iter().zip(frames) {
+        let row_bytes = frame.width as usize * 4;
+        for (row, src) in image.data().chunks_exact(row_bytes).enumerate() {
+            let start = (frame.y as usize + row) * stride + frame.x as usize * 4;
+            data[start..start + row_bytes].copy_from_slice(src);
+        }
+    }
+    Ok(sheet)
+}
@@ -0,0 +1,321 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+#[cfg(not(target_arch = "wasm32"))]
+use napi_derive::napi;
+use resvg::usvg::{self, NodeExt, NodeKind, TreeParsing, TreeTextToPath, fontdb::Database};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::BBox;
+use crate::error::Error;
+use crate::options::JsMeasureTextOptions;
+use crate::sprite::escape;
+
+/// A text chunk of the SVG, captured before the text is converted to paths.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+#[cfg_attr(not(target_arch = "wasm32"), napi(object, object_from_js = false))]
+#[derive(Clone, Debug)]
+pub struct TextChunk {
+    /// The id of the `<text>` element, empty if it has none.
+    pub id: String,
+    /// The text content of this chunk.
+    pub text: String,
+    /// The font families of the first span, comma separated.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = fontFamily))]
+    pub font_family: String,
+    /// The font size of the first span.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = fontSize))]
+    pub font_size: f64,
+    /// The fill of the first span, e.g. `#ff0000` or `url(#gradient)`.
+    pub fill: Option<String>,
+    /// The bounding box of this chunk in user space.
+    ///
+    /// `None` when no font could be found to shape the text.
+    pub bbox: Option<BBox>,
+}
+
+/// The position of a glyph cluster on the baseline, as laid out by usvg.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[cfg_attr(not(target_arch = "wasm32"), napi(object))]
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphPosition {
+    /// The byte offset of the first character of the cluster.
+    pub cluster: u32,
+    pub x: f64,
+    /// The horizontal advance, including letter spacing.
+    pub advance: f64,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[cfg_attr(not(target_arch = "wasm32"), napi)]
+pub struct TextMetrics {
+    width: f64,
+    ascent: f64,
+    descent: f64,
+    glyphs: Vec<GlyphPosition>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[napi]
+impl TextMetrics {
+    /// Get the advance width of the text
+    #[napi(getter)]
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    /// Get the font ascent above the baseline
+    #[napi(getter)]
+    pub fn ascent(&self) -> f64 {
+        self.ascent
+    }
+
+    /// Get the font descent below the baseline, as a positive number
+    #[napi(getter)]
+    pub fn descent(&self) -> f64 {
+        self.descent
+    }
+
+    /// Get the position of every glyph cluster
+    #[napi(getter)]
+    pub fn glyphs(&self) -> Vec<GlyphPosition> {
+        self.glyphs.clone()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl TextMetrics {
+    /// Get the advance width of the text
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    /// Get the font ascent above the baseline
+    #[wasm_bindgen(getter)]
+    pub fn ascent(&self) -> f64 {
+        self.ascent
+    }
+
+    /// Get the font descent below the baseline, as a positive number
+    #[wasm_bindgen(getter)]
+    pub fn descent(&self) -> f64 {
+        self.descent
+    }
+
+    /// Get the position of every glyph cluster
+    #[wasm_bindgen(getter)]
+    pub fn glyphs(&self) -> js_sys::Array {
+        js_sys::Array::from_iter(self.glyphs.iter().map(|g| wasm_bindgen::JsValue::from(*g)))
+    }
+}
+
+/// Lays out a single line of text with usvg, exactly like `render()` does.
+///
+/// Every character is a `<tspan>` filled with its index as color, so that the paths of
+/// `convert_text()` can be mapped back to the characters. A `.` in its own text chunk follows,
+/// it starts where the text ends, trailing whitespace included.
+pub(crate) fn measure_text(
+    text: &str,
+    options: &JsMeasureTextOptions,
+    fontdb: &Database,
+) -> Result<TextMetrics, Error> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut svg = String::from(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="1" height="1"><text xml:space="preserve""#,
+    );
+    if let Some(font_family) = &options.font_family {
+        svg.push_str(&format!(r#" font-family="{}""#, escape(font_family)));
+    }
+    if let Some(font_size) = options.font_size {
+        svg.push_str(&format!(r#" font-size="{font_size}""#));
+    }
+    svg.push_str(&format!(
+        r#" font-weight="{}" letter-spacing="{}">"#,
+        options.weight, options.letter_spacing
+    ));
+    let mut buffer = [0; 4];
+    for (index, (_, c)) in chars.iter().enumerate() {
+        let c = escape(c.encode_utf8(&mut buffer));
+        svg.push_str(&format!(r##"<tspan fill="#{index:06x}">{c}</tspan>"##));
+    }
+    svg.push_str(&format!(
+        r##"<tspan y="0" fill="#{:06x}">.</tspan></text></svg>"##,
+        chars.len()
+    ));
+
+    let usvg_options = usvg::Options {
+        font_family: options.font.default_font_family.clone(),
+        font_size: options.font.default_font_size,
+        ..usvg::Options::default()
+    };
+    let mut tree = usvg::Tree::from_str(&svg, &usvg_options)?;
+    tree.convert_text(fontdb);
+
+    // The bbox based on the font metrics of every cluster, indexed by its first character.
+    let mut boxes: Vec<Option<usvg::NonZeroRect>> = vec![None; chars.len() + 1];
+    for node in tree.root.descendants() {
+        if let NodeKind::Path(path) = &*node.borrow() {
+            let color = match path.fill.as_ref().map(|fill| &fill.paint) {
+                Some(usvg::Paint::Color(color)) => color,
+                _ => continue,
+            };
+            let index =
+                (color.red as usize) << 16 | (color.green as usize) << 8 | color.blue as usize;
+            if let Some(slot) = boxes.get_mut(index) {
+                *slot = path.text_bbox;
+            }
+        }
+    }
+    let end = boxes[chars.len()].ok_or_else(|| {
+        Error::FontNotFound(
+            options
+                .font_family
+                .clone()
+                .unwrap_or_else(|| options.font.default_font_family.clone()),
+        )
+    })?;
+
+    let mut glyphs = vec![];
+    let mut x = 0.0;
+    // Whitespace has no outline, it shares out the gap up to the next cluster.
+    let mut whitespace = vec![];
+    for (&(offset, c), bbox) in chars.iter().zip(&boxes) {
+        match bbox {
+            Some(bbox) => {
+                place_whitespace(&mut glyphs, &mut whitespace, x, bbox.x());
+                glyphs.push(GlyphPosition {
+                    cluster: offset as u32,
+                    x: bbox.x() as f64,
+                    advance: bbox.width() as f64,
+                });
+                x = bbox.right();
+            }
+            None if c.is_whitespace() => whitespace.push(offset),
+            // A character of the previous cluster, e.g. of a ligature or a combining mark.
+            None => {}
+        }
+    }
+    place_whitespace(&mut glyphs, &mut whitespace, x, end.x());
+
+    let ascent = boxes
+        .iter()
+        .flatten()
+        .map(|bbox| -bbox.y())
+        .fold(0.0, f32::max);
+    let descent = boxes
+        .iter()
+        .flatten()
+        .map(|bbox| bbox.bottom())
+        .fold(0.0, f32::max);
+    Ok(TextMetrics {
+        width: end.x() as f64,
+        ascent: ascent as f64,
+        descent: descent as f64,
+        glyphs,
+    })
+}
+
+fn place_whitespace(
+    glyphs: &mut Vec<GlyphPosition>,
+    whitespace: &mut Vec<usize>,
+    start: f32,
+    end: f32,
+) {
+    let advance = (end - start) / whitespace.len().max(1) as f32;
+    for (i, offset) in whitespace.drain(..).enumerate() {
+        glyphs.push(GlyphPosition {
+            cluster: offset as u32,
+            x: (start + advance * i as f32) as f64,
+            advance: advance as f64,
+        });
+    }
+}
+
+/// Captures all text nodes of the tree, with their absolute transform applied.
+///
+/// This must be called before `convert_text()`, since text nodes are replaced by paths afterwards.
+pub(crate) fn capture_text_nodes(tree: &usvg::Tree) -> Vec<usvg::Text> {
+    let mut texts = vec![];
+    for node in tree.root.descendants() {
+        if let NodeKind::Text(text) = &*node.borrow() {
+            let mut text = text.clone();
+            text.transform = node.abs_transform();
+            texts.push(text);
+        }
+    }
+    texts
+}
+
+/// Splits the captured text nodes into chunks and shapes each one to get its bounding box.
+pub(crate) fn text_chunks(
+    texts: &[usvg::Text],
+    tree: &usvg::Tree,
+    fontdb: &Database,
+) -> Vec<TextChunk> {
+    let mut chunks = vec![];
+    for text in texts {
+        // `positions` and `rotate` are indexed by characters across all chunks.
+        let mut char_offset = 0;
+        for chunk in &text.chunks {
+            let char_count = chunk.text.chars().count();
+            let range = char_offset..char_offset + char_count;
+            char_offset += char_count;
+
+            let span = match chunk.spans.first() {
+                Some(span) => span,
+                None => continue,
+            };
+
+            // Shape this chunk alone to get its own bounding box.
+            let mut chunk_text = text.clone();
+            chunk_text.chunks = vec![chunk.clone()];
+            chunk_text.positions = text
+                .positions
+                .get(range.clone())
+                .map(|p| p.to_vec())
+                .unwrap_or_default();
+            chunk_text.rotate = text
+                .rotate
+                .get(range)
+                .map(|r| r.to_vec())
+                .unwrap_or_default();
+
+            let mut chunk_tree = usvg::Tree {
+                size: tree.size,
+                view_box: tree.view_box,
+                root: usvg::Node::new(NodeKind::Group(usvg::Group::default())),
+            };
+            chunk_tree.root.append_kind(NodeKind::Text(chunk_text));
+            chunk_tree.convert_text(fontdb);
+            let bbox = chunk_tree.root.calculate_bbox().map(|bbox| BBox {
+                x: bbox.x() as f64,
+                y: bbox.y() as f64,
+                width: bbox.width() as f64,
+                height: bbox.height() as f64,
+            });
+
+            chunks.push(TextChunk {
+                id: text.id.clone(),
+                text: chunk.text.clone(),
+                font_family: span.font.families.join(", "),
+                font_size: span.font_size.get() as f64,
+                fill: span.fill.as_ref().map(|fill| paint_to_string(&fill.paint)),
+                bbox,
+            });
+        }
+    }
+    chunks
+}
+
+fn paint_to_string(paint: &usvg::Paint) -> String {
+    match paint {
+        usvg::Paint::Color(c) => format!("#{:02x}{:02x}{:02x}", c.red, c.green, c.blue),
+        usvg::Paint::LinearGradient(lg) => format!("url(#{})", lg.id),
+        usvg::Paint::RadialGradient(rg) => format!("url(#{})", rg.id),
+        usvg::Paint::Pattern(p) => format!("url(#{})", p.id),
+    }
+}
@@ -0,0 +1,310 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::io::{self, Write};
+use std::num::NonZeroU32;
+
+use resvg::tiny_skia::{IntRect, Pixmap};
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::options::{FitToDef, ImageFormat};
+
+/// The javascript options passed to `renderTiles()`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsRenderTilesOptions {
+    /// The width and height of the tiles, the tiles of the last column and row can be smaller.
+    ///
+    /// Default: 512
+    pub tile_size: NonZeroU32,
+
+    /// The size of the whole image.
+    ///
+    /// Default: the `fitTo` option
+    pub fit_to: Option<FitToDef>,
+
+    /// The format of the tiles.
+    ///
+    /// Default: rgba
+    pub format: ImageFormat,
+}
+
+impl Default for JsRenderTilesOptions {
+    fn default() -> Self {
+        JsRenderTilesOptions {
+            tile_size: NonZeroU32::new(512).unwrap(),
+            fit_to: None,
+            format: ImageFormat::default(),
+        }
+    }
+}
+
+/// The javascript options passed to `renderPngStream()`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsPngStreamOptions {
+    /// The number of rows rendered at once, only this strip of the image is held in memory.
+    ///
+    /// Default: 256
+    pub strip_height: NonZeroU32,
+
+    /// The size of the whole image.
+    ///
+    /// Default: the `fitTo` option
+    pub fit_to: Option<FitToDef>,
+}
+
+impl Default for JsPngStreamOptions {
+    fn default() -> Self {
+        JsPngStreamOptions {
+            strip_height: NonZeroU32::new(256).unwrap(),
+            fit_to: None,
+        }
+    }
+}
+
+/// Splits a `width` x `height` image into tiles, row by row.
+pub(crate) fn tile_grid(
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+) -> impl Iterator<Item = IntRect> {
+    (0..height)
+        .step_by(tile_height as usize)
+        .flat_map(move |y| {
+            (0..width)
+                .step_by(tile_width as usize)
+                .filter_map(move |x| {
+                    IntRect::from_xywh(
+                        x as i32,
+                        y as i32,
+                        tile_width.min(width - x),
+                        tile_height.min(height - y),
+                    )
+                })
+        })
+}
+
+/// The PNG bytes are handed to the callback in chunks of about this size.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Collects the encoded PNG and hands it to `emit` in chunks.
+pub(crate) struct ChunkWriter<E, F: FnMut(Vec<u8>) -> Result<(), E>> {
+    buffer: Vec<u8>,
+    emit: F,
+    // The error of `emit`, which `io::Write` cannot carry.
+    error: Option<E>,
+}
+
+impl<E, F: FnMut(Vec<u8>) -> Result<(), E>> ChunkWriter<E, F> {
+    pub(crate) fn new(emit: F) -> Self {
+        ChunkWriter {
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+            emit,
+            error: None,
+        }
+    }
+
+    fn emit_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE));
+        (self.emit)(chunk).map_err(|e| {
+            self.error = Some(e);
+            io::Error::other("the callback failed")
+        })
+    }
+}
+
+impl<E, F: FnMut(Vec<u8>) -> Result<(), E>> Write for ChunkWriter<E, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.emit_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.emit_buffer()
+    }
+}
+
+/// Why encoding a PNG stopped.
+enum Failure<E> {
+    Render(E),
+    Encoding(png::EncodingError),
+}
+
+/// Encodes a `width` x `height` PNG into `out`, rendering it strip by strip with `render`.
+///
+/// Only one strip is held in memory, the rows are demultiplied and compressed as they come.
+pub(crate) fn write_png<E: From<Error>, F: FnMut(Vec<u8>) -> Result<(), E>>(
+    out: &mut ChunkWriter<E, F>,
+    width: u32,
+    height: u32,
+    strip_height: u32,
+    mut render: impl FnMut(IntRect) -> Result<Pixmap, E>,
+) -> Result<(), E> {
+    let mut encoder = png::Encoder::new(&mut *out, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let result = (|| {
+        let mut writer = encoder.write_header().map_err(Failure::Encoding)?;
+        let mut stream = writer.stream_writer().map_err(Failure::Encoding)?;
+        for strip in tile_grid(width, height, width, strip_height) {
+            let pixmap = render(strip).map_err(Failure::Render)?;
+            let mut rows = Vec::with_capacity(pixmap.data().len());
+            for pixel in pixmap.pixels() {
+                let color = pixel.demultiply();
+                rows.extend_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+            }
+            stream
+                .write_all(&rows)
+                .map_err(|e| Failure::Encoding(e.into()))?;
+        }
+        stream.finish().map_err(Failure::Encoding)?;
+        writer.finish().map_err(Failure::Encoding)?;
+        Ok(())
+    })();
+    let result = result.and_then(|()| out.flush().map_err(|e| Failure::Encoding(e.into())));
+    match result {
+        Ok(()) => Ok(()),
+        Err(Failure::Render(e)) => Err(e),
+        // Rethrows the error of the callback rather than the io error wrapping it.
+        Err(Failure::Encoding(e)) => Err(out.error.take().unwrap_or_else(|| Error::from(e).into())),
+    }
+}
+
+/// The javascript options passed to `exportTilePyramid()`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct JsTilePyramidOptions {
+    /// The width and height of the tiles, without the overlap.
+    ///
+    /// Default: 254
+    pub tile_size: NonZeroU32,
+
+    /// The pixels shared with the neighbouring tiles on each side.
+    ///
+    /// Default: 1
+    pub overlap: u32,
+
+    /// The format of the tiles.
+    ///
+    /// Default: png
+    pub format: ImageFormat,
+
+    /// The first exported level, the image is 1x1 at level 0.
+    ///
+    /// Default: 0
+    pub min_level: u32,
+
+    /// The last exported level, clamped to the level of the full size image.
+    ///
+    /// Default: the level of the full size image
+    pub max_level: Option<u32>,
+}
+
+impl Default for JsTilePyramidOptions {
+    fn default() -> Self {
+        JsTilePyramidOptions {
+            tile_size: NonZeroU32::new(254).unwrap(),
+            overlap: 1,
+            format: ImageFormat::Png,
+            min_level: 0,
+            max_level: None,
+        }
+    }
+}
+
+/// The levels of a Deep Zoom image, level `full_level` is the full size image
+/// and every level below is half the size of the next one, rounded up.
+pub(crate) struct Pyramid {
+    pub width: u32,
+    pub height: u32,
+    pub full_level: u32,
+}
+
+/// The position of a tile in the pyramid, `rect` is in the pixels of its level.
+pub(crate) struct PyramidTile {
+    pub level: u32,
+    pub column: u32,
+    pub row: u32,
+    pub rect: IntRect,
+}
+
+impl Pyramid {
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        // The smallest level where the image fits in 1x1 after halving, i.e. ceil(log2(size)).
+        let full_level = u32::BITS - (width.max(height) - 1).leading_zeros();
+        Pyramid {
+            width,
+            height,
+            full_level,
+        }
+    }
+
+    /// The scale of `level` relative to the full size image.
+    pub(crate) fn scale(&self, level: u32) -> f32 {
+        0.5f32.powi((self.full_level - level) as i32)
+    }
+
+    /// The size of the image at `level`.
+    pub(crate) fn level_size(&self, level: u32) -> (u32, u32) {
+        let shift = self.full_level - level;
+        let halve = |size: u32| (size as u64).div_ceil(1 << shift) as u32;
+        (halve(self.width), halve(self.height))
+    }
+
+    /// The tiles of `level`, row by row.
+    pub(crate) fn tiles(
+        &self,
+        level: u32,
+        tile_size: u32,
+        overlap: u32,
+    ) -> impl Iterator<Item = PyramidTile> {
+        let (width, height) = self.level_size(level);
+        let span = move |index: u32, size: u32| {
+            let start = (index * tile_size).saturating_sub(overlap);
+            let end = ((index + 1).saturating_mul(tile_size))
+                .saturating_add(overlap)
+                .min(size);
+            (start, end - start)
+        };
+        (0..height.div_ceil(tile_size)).flat_map(move |row| {
+            (0..width.div_ceil(tile_size)).filter_map(move |column| {
+                let (x, tile_width) = span(column, width);
+                let (y, tile_height) = span(row, height);
+                let rect = IntRect::from_xywh(x as i32, y as i32, tile_width, tile_height)?;
+                Some(PyramidTile {
+                    level,
+                    column,
+                    row,
+                    rect,
+                })
+            })
+        })
+    }
+
+    /// The `.dzi` descriptor, tiles are expected at `{name}_files/{level}/{column}_{row}.png`.
+    ///
+    /// `None` for raw RGBA tiles, which are not a tile format that viewers can load.
+    pub(crate) fn dzi(&self, options: &JsTilePyramidOptions) -> Option<String> {
+        if options.format != ImageFormat::Png {
+            return None;
+        }
+        Some(format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Image xmlns="http://schemas.microsoft.com/deepzoom/2008" Format="png" Overlap="{}" TileSize="{}">
+  <Size Width="{}" Height="{}"/>
+</Image>
+"#,
+            options.overlap, options.tile_size, self.width, self.height
+        ))
+    }
+}
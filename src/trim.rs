@@ -0,0 +1,65 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use resvg::tiny_skia::{IntRect, Pixmap};
+
+use crate::error::Error;
+use crate::options::JsTrimOptions;
+
+/// Finds the smallest rectangle containing every pixel whose alpha is above `threshold`.
+///
+/// Returns `None` if there is no such pixel.
+pub(crate) fn opaque_bounds(pixmap: &Pixmap, threshold: u8) -> Option<IntRect> {
+    let width = pixmap.width() as usize;
+    let opaque = |row: &[u8]| row.chunks_exact(4).position(|px| px[3] > threshold);
+    let rows: Vec<&[u8]> = pixmap.data().chunks_exact(width * 4).collect();
+
+    let top = rows.iter().position(|row| opaque(row).is_some())?;
+    let bottom = rows.iter().rposition(|row| opaque(row).is_some())?;
+    let (mut left, mut right) = (width, 0);
+    for row in &rows[top..=bottom] {
+        if let Some(x) = opaque(row) {
+            left = left.min(x);
+            let last = row.chunks_exact(4).rposition(|px| px[3] > threshold);
+            right = right.max(last.unwrap_or(x));
+        }
+    }
+    IntRect::from_ltrb(left as i32, top as i32, right as i32 + 1, bottom as i32 + 1)
+}
+
+/// The size of `pixmap` after trimming, `None` if it is left unchanged.
+pub(crate) fn trimmed_size(
+    pixmap: &Pixmap,
+    options: &JsTrimOptions,
+) -> Option<(IntRect, u32, u32)> {
+    let bounds = opaque_bounds(pixmap, options.threshold)?;
+    let width = bounds
+        .width()
+        .saturating_add(options.padding.saturating_mul(2));
+    let height = bounds
+        .height()
+        .saturating_add(options.padding.saturating_mul(2));
+    Some((bounds, width, height))
+}
+
+/// Copies `bounds` of `pixmap` into a new pixmap, with `padding` transparent pixels on every side.
+pub(crate) fn trim(
+    pixmap: &Pixmap,
+    bounds: IntRect,
+    width: u32,
+    height: u32,
+    padding: u32,
+) -> Result<Pixmap, Error> {
+    let mut trimmed = Pixmap::new(width, height).ok_or(Error::AllocationFailed)?;
+    let src_stride = pixmap.width() as usize * 4;
+    let dst_stride = width as usize * 4;
+    let row_bytes = bounds.width() as usize * 4;
+    let data = trimmed.data_mut();
+    for row in 0..bounds.height() as usize {
+        let src = (bounds.y() as usize + row) * src_stride + bounds.x() as usize * 4;
+        let dst = (padding as usize + row) * dst_stride + padding as usize * 4;
+        data[dst..dst + row_bytes].copy_from_slice(&pixmap.data()[src..src + row_bytes]);
+    }
+    Ok(trimmed)
+}
@@ -1,5 +1,28 @@
-import init, { Resvg as _Resvg, InitInput } from './wasm/dist'
-import { CustomFontsOptions, ResvgRenderOptions, SystemFontsOptions } from './wasm/index'
+import init, {
+  BBox,
+  Resvg as _Resvg,
+  measureText as _measureText,
+  packSpriteSheet as _packSpriteSheet,
+  setLogger as _setLogger,
+  setLogLevel as _setLogLevel,
+  InitInput,
+} from './wasm/dist'
+import {
+  CropByBBoxOptions,
+  CustomFontsOptions,
+  ElementBBoxOptions,
+  LogLevel,
+  MeasureTextOptions,
+  PackSpriteSheetOptions,
+  RenderElementOptions,
+  PngStreamOptions,
+  RenderRegionOptions,
+  RenderSymbolsOptions,
+  RenderTilesOptions,
+  ResvgRenderOptions,
+  SystemFontsOptions,
+  TilePyramidOptions,
+} from './wasm/index'
 
 let initialized = false
 
@@ -35,11 +58,182 @@ export const Resvg = class extends _Resvg {
         },
       }
 
-      super(svg, JSON.stringify(serializableOptions), font.fontBuffers)
+      super(svg, JSON.stringify(serializableOptions), font.fontBuffers, options?.imageResolver)
     } else {
-      super(svg, JSON.stringify(options))
+      super(svg, JSON.stringify(options), undefined, options?.imageResolver)
     }
   }
+
+  /**
+   * Calculate a maximum bounding box of all visible elements in this SVG
+   * @param {ElementBBoxOptions | undefined} options
+   */
+  innerBBox(options?: ElementBBoxOptions | string) {
+    return super.innerBBox(typeof options === 'string' ? options : JSON.stringify(options))
+  }
+
+  /**
+   * Calculate the bounding box of the element with `id` in user space
+   * @param {string} id
+   * @param {ElementBBoxOptions | undefined} options
+   */
+  getElementBBox(id: string, options?: ElementBBoxOptions | string) {
+    return super.getElementBBox(id, typeof options === 'string' ? options : JSON.stringify(options))
+  }
+
+  /**
+   * Calculate the bounding box of every element with an id, in user space and in output pixels
+   * @param {ElementBBoxOptions | undefined} options
+   */
+  elementBBoxes(options?: ElementBBoxOptions | string) {
+    return super.elementBBoxes(typeof options === 'string' ? options : JSON.stringify(options))
+  }
+
+  /**
+   * Use a given `BBox` to crop the svg
+   * @param {BBox} bbox
+   * @param {number | CropByBBoxOptions | undefined} padding
+   * @param {boolean | undefined} square
+   * @param {boolean | undefined} relative
+   */
+  cropByBBox(bbox: BBox, padding?: number | CropByBBoxOptions, square?: boolean, relative?: boolean) {
+    if (typeof padding === 'object' && padding !== null) {
+      return super.cropByBBoxWithOptions(bbox, JSON.stringify(padding))
+    }
+    return super.cropByBBox(bbox, padding, square, relative)
+  }
+
+  /**
+   * Render only the element with `id` and its subtree
+   * @param {string} id
+   * @param {RenderElementOptions | undefined} options
+   */
+  // `string` keeps this override compatible with the raw binding, which takes the options as JSON.
+  renderElement(id: string, options?: RenderElementOptions | string) {
+    return super.renderElement(id, typeof options === 'string' ? options : JSON.stringify(options))
+  }
+
+  /**
+   * Render every `<symbol>` and top-level group with an id
+   * @param {RenderSymbolsOptions | undefined} options
+   */
+  renderSymbols(options?: RenderSymbolsOptions | string) {
+    return super.renderSymbols(typeof options === 'string' ? options : JSON.stringify(options))
+  }
+
+  /**
+   * Render a rectangle of user space at a given scale, without changing the viewbox
+   * @param {{ x: number; y: number; width: number; height: number }} region
+   * @param {RenderRegionOptions | undefined} options
+   */
+  // @ts-expect-error the raw binding takes the region as numbers.
+  renderRegion(region: { x: number; y: number; width: number; height: number }, options?: RenderRegionOptions | string) {
+    return super.renderRegion(
+      region.x,
+      region.y,
+      region.width,
+      region.height,
+      typeof options === 'string' ? options : JSON.stringify(options),
+    )
+  }
+
+  /**
+   * Render the image tile by tile, row by row, only the current tile is held in memory
+   * @param {RenderTilesOptions | undefined} options
+   * @param {Function} callback
+   */
+  renderTiles(options: RenderTilesOptions | string | undefined, callback: Function) {
+    return super.renderTiles(typeof options === 'string' ? options : JSON.stringify(options), callback)
+  }
+
+  /**
+   * Render every level of a Deep Zoom (DZI) pyramid from the vector tree, e.g. for OpenSeadragon
+   * @param {TilePyramidOptions | undefined} options
+   */
+  exportTilePyramid(options?: TilePyramidOptions | string) {
+    return super.exportTilePyramid(typeof options === 'string' ? options : JSON.stringify(options))
+  }
+
+  /**
+   * Encode the image as a PNG without holding the whole image in memory
+   * @param {PngStreamOptions | undefined} options
+   * @param {Function} callback
+   */
+  renderPngStream(options: PngStreamOptions | string | undefined, callback: Function) {
+    return super.renderPngStream(typeof options === 'string' ? options : JSON.stringify(options), callback)
+  }
+}
+
+/**
+ * Measure a single line of text with the same fonts and shaping used to render SVG text
+ * @param {string} text
+ * @param {MeasureTextOptions | undefined} options
+ */
+export const measureText = (text: string, options?: MeasureTextOptions) => {
+  if (!initialized) throw new Error('Wasm has not been initialized. Call `initWasm()` function.')
+
+  const font = options?.font
+
+  if (!!font && isCustomFontsOptions(font)) {
+    const serializableOptions = {
+      ...options,
+      font: {
+        ...font,
+        fontBuffers: undefined,
+      },
+    }
+
+    return _measureText(text, JSON.stringify(serializableOptions), font.fontBuffers)
+  }
+  return _measureText(text, JSON.stringify(options))
+}
+
+/**
+ * Render several SVGs into one sprite sheet, packed in rows
+ * @param {Array<Uint8Array | string>} svgs
+ * @param {PackSpriteSheetOptions | undefined} options
+ */
+export const packSpriteSheet = (svgs: Array<Uint8Array | string>, options?: PackSpriteSheetOptions) => {
+  if (!initialized) throw new Error('Wasm has not been initialized. Call `initWasm()` function.')
+
+  const { padding, maxWidth, ...renderOptions } = options ?? {}
+  const packOptions = JSON.stringify({ padding, maxWidth })
+  const font = renderOptions.font
+
+  if (!!font && isCustomFontsOptions(font)) {
+    const serializableOptions = {
+      ...renderOptions,
+      font: {
+        ...font,
+        fontBuffers: undefined,
+      },
+    }
+
+    return _packSpriteSheet(svgs, JSON.stringify(serializableOptions), packOptions, font.fontBuffers)
+  }
+  return _packSpriteSheet(svgs, JSON.stringify(renderOptions), packOptions)
+}
+
+/**
+ * Forward the log records to `callback`, pass `null` to remove it
+ * @param {((level: string, target: string, message: string) => void) | null} callback
+ * @param {LogLevel | undefined} level Changes the log level like `setLogLevel()`
+ */
+export const setLogger = (
+  callback: ((level: Exclude<LogLevel, 'off'>, target: string, message: string) => void) | null,
+  level?: LogLevel,
+) => {
+  if (!initialized) throw new Error('Wasm has not been initialized. Call `initWasm()` function.')
+  _setLogger(callback ?? undefined, level)
+}
+
+/**
+ * Change the log level of the instances without a `logLevel` option, at runtime
+ * @param {LogLevel} level
+ */
+export const setLogLevel = (level: LogLevel) => {
+  if (!initialized) throw new Error('Wasm has not been initialized. Call `initWasm()` function.')
+  _setLogLevel(level)
 }
 
 function isCustomFontsOptions(value: SystemFontsOptions | CustomFontsOptions): value is CustomFontsOptions {
@@ -23,6 +23,12 @@ declare class RenderedImage {
 	*/
 	asPng(): Uint8Array;
 	/**
+	* Get the smallest rectangle containing every pixel whose alpha is above `threshold` (default: 0)
+	* @param {number | undefined} threshold
+	* @returns {BBox | undefined}
+	*/
+	opaqueBounds(threshold?: number): BBox | undefined;
+	/**
 	* Get the PNG height
 	*/
 	readonly height: number;
@@ -35,6 +41,25 @@ declare class RenderedImage {
 	*/
 	readonly width: number;
 }
+declare class TextMetrics {
+	free(): void;
+	/**
+	* Get the font ascent above the baseline
+	*/
+	readonly ascent: number;
+	/**
+	* Get the font descent below the baseline, as a positive number
+	*/
+	readonly descent: number;
+	/**
+	* Get the position of every glyph cluster
+	*/
+	readonly glyphs: any[];
+	/**
+	* Get the advance width of the text
+	*/
+	readonly width: number;
+}
 export type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
 export type ResvgRenderOptions = {
 	font?: SystemFontsOptions | CustomFontsOptions;
@@ -61,12 +86,100 @@ export type ResvgRenderOptions = {
 		value: number;
 	};
 	background?: string; // Support CSS3 color, e.g. rgba(255, 255, 255, .8)
+	// Numbers are output pixels, or user space with `units: "user"`. Percentages like "25%" are relative to the output size.
+	// Throws `ERR_INVALID_CROP` when the rectangle is empty, inverted or outside of the image.
 	crop?: {
-		left: number;
-		top: number;
-		right?: number;
-		bottom?: number;
+		left: number | `${number}%`;
+		top: number | `${number}%`;
+		right?: number | `${number}%`;
+		bottom?: number | `${number}%`;
+		units?: "pixels" | "user"; // Default: "pixels"
+	};
+	// Trim the transparent pixels around the content after `crop`, e.g. `trim: {}`.
+	// Fully transparent images are kept as they are, and nothing is trimmed with an opaque `background`.
+	trim?: {
+		threshold?: number; // Default: 0, pixels with an alpha at or below it (0-255) are trimmed.
+		padding?: number; // Default: 0, the transparent pixels kept around the content.
+	};
+	lenient?: boolean; // Default: false, fall back to the default options instead of throwing when the options are invalid.
+	logLevel?: LogLevel; // Default: the level set by `setLogLevel()`, "error" unless changed. Only applies to this instance.
+	limits?: {
+		maxImagePixels?: number; // The maximum width * height of a single image, read from its header before decoding.
+		maxImageBytes?: number; // The maximum encoded size of a single image in bytes.
+		maxImages?: number; // The maximum number of images in the SVG.
+		skipOversizedImages?: boolean; // Default: false, skip images over the limits instead of throwing.
+		maxWidth?: number; // The maximum width of the rendered image, after `fitTo` is applied.
+		maxHeight?: number; // The maximum height of the rendered image, after `fitTo` is applied.
+		maxPixels?: number; // The maximum width * height of the rendered image.
+		maxNodes?: number; // The maximum number of elements, counted with every `<use>` expanded.
+		maxUseDepth?: number; // The maximum nesting depth of `<use>` references.
+		maxDecompressedBytes?: number; // Default: 100 MiB, the maximum size of a decompressed SVGZ input.
+		renderTimeout?: number; // The maximum time in milliseconds spent parsing, and then again rendering.
 	};
+	/**
+	 * Resolve http(s) image hrefs while parsing, including images nested in patterns, masks and clip paths.
+	 * Return `null` to leave the image unresolved, it can still be resolved later by `resolveImage()`.
+	 * Returning data that is not a supported image throws `ERR_UNSUPPORTED_IMAGE`, AVIF throws `ERR_UNDECODABLE_IMAGE`.
+	 */
+	imageResolver?: (href: string) => Uint8Array | null;
+};
+export type RenderElementOptions = {
+	fitTo?: ResvgRenderOptions["fitTo"]; // Default: { mode: "original" }, applied to the bounding box including the padding.
+	padding?: number; // Default: 0, extra space around the bounding box in user units, e.g. to include strokes.
+};
+export type RenderSymbolsOptions = {
+	fitTo?: ResvgRenderOptions["fitTo"]; // Default: { mode: "original" }, applied to every symbol.
+	format?: "rgba" | "png"; // Default: "rgba", a `RenderedImage`. "png" returns PNG encoded Uint8Arrays instead.
+};
+// Throws `ERR_INVALID_REGION` when the region is empty, or when both options are set.
+export type RenderRegionOptions = {
+	scale?: number; // Default: 1, the number of pixels per user unit.
+	outputWidth?: number; // The width of the image in pixels, the height follows the aspect ratio of the region.
+};
+export type RenderTilesOptions = {
+	tileSize?: number; // Default: 512, the tiles of the last column and row can be smaller.
+	fitTo?: ResvgRenderOptions["fitTo"]; // Default: the `fitTo` option, the size of the whole image.
+	format?: "rgba" | "png"; // Default: "rgba", a `RenderedImage`. "png" returns PNG encoded Uint8Arrays instead.
+};
+export type TilePyramidOptions = {
+	tileSize?: number; // Default: 254, the size of the tiles without the overlap.
+	overlap?: number; // Default: 1, the pixels shared with the neighbouring tiles on each side.
+	format?: "png" | "rgba"; // Default: "png", PNG encoded Uint8Arrays. "rgba" returns `RenderedImage`s instead.
+	minLevel?: number; // Default: 0, the first exported level. The image is 1x1 at level 0.
+	maxLevel?: number; // Default: the level of the full size image, larger values are clamped to it.
+	// Throws `ERR_INVALID_LEVELS` when `minLevel` is greater than the clamped `maxLevel`.
+};
+export type PngStreamOptions = {
+	stripHeight?: number; // Default: 256, the number of rows rendered at once.
+	fitTo?: ResvgRenderOptions["fitTo"]; // Default: the `fitTo` option, the size of the whole image.
+};
+export type PackSpriteSheetOptions = Omit<ResvgRenderOptions, "imageResolver"> & {
+	padding?: number; // Default: 0, the space between two frames in pixels.
+	maxWidth?: number; // Default: about the square root of the total area, frames wrap to a new row past it.
+};
+export type CropByBBoxOptions = {
+	padding?: number | { top?: number; right?: number; bottom?: number; left?: number }; // Default: 0, the bleed area in output pixels.
+	square?: boolean; // Default: false, make the crop area square using the larger dimension.
+	aspectRatio?: number; // Grow the crop area to this width / height ratio, like `square` for 1.
+	relative?: boolean; // Default: false, position the bbox relative to the top-left corner of the current crop.
+	normalize?: boolean; // Default: false, move the content so that the cropped viewbox starts at 0,0, e.g. for `toString()`.
+};
+export type ElementBBoxOptions = {
+	/**
+	 * Default: "fill", the geometry of the shapes like `SVGGraphicsElement.getBBox()`.
+	 * "stroke" includes the strokes, "visual" only the painted area clipped by clip paths, masks and the canvas.
+	 * "ink" is "visual" with the exact stroke joins, miter limit and dashes, and the filter regions, e.g. drop shadows.
+	 */
+	kind?: "fill" | "stroke" | "visual" | "ink";
+};
+export type LogLevel = "off" | "error" | "warn" | "info" | "debug" | "trace";
+export type MeasureTextOptions = {
+	font?: SystemFontsOptions | CustomFontsOptions;
+	fontFamily?: string; // A CSS-like list of font families, e.g. "Pacifico, serif". Default: the default font family
+	fontSize?: number; // Default: the default font size
+	weight?: number; // Default: 400
+	letterSpacing?: number; // Default: 0
+	lenient?: boolean; // Default: false, fall back to the default options instead of throwing when the options are invalid.
 };
 export type FontOptions = {
 	defaultFontSize?: number; // Default: 12
@@ -85,20 +198,171 @@ export type SystemFontsOptions = {
 	fontFiles?: string[]; // A list of local font file paths to load.
 	fontDirs?: string[]; // A list of local font directories to load.
 } & FontOptions;
+export type ResvgErrorCode = "ERR_INVALID_COLOR" | "ERR_USVG_PARSE" | "ERR_PNG_ENCODING" | "ERR_INVALID_UTF8" | "ERR_ZERO_SIZED" | "ERR_ALLOCATION_FAILED" | "ERR_RENDER_PANIC" | "ERR_INVALID_OPTIONS" | "ERR_INVALID_LOG_LEVEL" | "ERR_READ_FILE" | "ERR_INVALID_INPUT" | "ERR_UNSUPPORTED_IMAGE" | "ERR_UNDECODABLE_IMAGE" | "ERR_IMAGE_DECODING" | "ERR_IMAGE_BYTES_LIMIT" | "ERR_IMAGE_PIXELS_LIMIT" | "ERR_IMAGE_COUNT_LIMIT" | "ERR_WIDTH_LIMIT" | "ERR_HEIGHT_LIMIT" | "ERR_PIXELS_LIMIT" | "ERR_DECOMPRESSED_SIZE_LIMIT" | "ERR_NODE_COUNT_LIMIT" | "ERR_USE_DEPTH_LIMIT" | "ERR_RENDER_TIMEOUT" | "ERR_FONT_NOT_FOUND" | "ERR_ELEMENT_NOT_FOUND" | "ERR_EMPTY_ELEMENT" | "ERR_INVALID_CROP" | "ERR_INVALID_REGION" | "ERR_INVALID_LEVELS";
+/** The errors thrown by resvg-js. */
+export interface ResvgError extends Error {
+	code: ResvgErrorCode;
+	/** The 1-based line of an XML parsing error (`ERR_USVG_PARSE`). */
+	line?: number;
+	/** The 1-based column of an XML parsing error (`ERR_USVG_PARSE`). */
+	column?: number;
+}
 /**
  * Initialize Wasm module
  * @param module_or_path WebAssembly Module or .wasm url
  *
  */
 export declare const initWasm: (module_or_path: Promise<InitInput> | InitInput) => Promise<void>;
+/**
+ * Measure a single line of text with the same fonts and shaping used to render SVG text
+ * @param {string} text
+ * @param {MeasureTextOptions | undefined} options
+ */
+export declare const measureText: (text: string, options?: MeasureTextOptions) => TextMetrics;
+/**
+ * Render several SVGs into one sprite sheet, packed in rows
+ * @param {Array<Uint8Array | string>} svgs
+ * @param {PackSpriteSheetOptions | undefined} options
+ */
+export declare const packSpriteSheet: (svgs: Array<Uint8Array | string>, options?: PackSpriteSheetOptions) => {
+	image: RenderedImage;
+	frames: {
+		x: number;
+		y: number;
+		width: number;
+		height: number;
+	}[];
+};
+/**
+ * Forward the log records to `callback`, pass `null` to remove it
+ * @param {((level: string, target: string, message: string) => void) | null} callback
+ * @param {LogLevel | undefined} level Changes the log level like `setLogLevel()`
+ */
+export declare const setLogger: (callback: ((level: Exclude<LogLevel, "off">, target: string, message: string) => void) | null, level?: LogLevel) => void;
+/**
+ * Change the log level of the instances without a `logLevel` option, at runtime
+ * @param {LogLevel} level
+ */
+export declare const setLogLevel: (level: LogLevel) => void;
 export declare const Resvg: {
 	new (svg: Uint8Array | string, options?: ResvgRenderOptions): {
 		free(): void;
+		/**
+		 * Render only the element with `id` and its subtree
+		 * @param {string} id
+		 * @param {RenderElementOptions | undefined} options
+		 */
+		renderElement(id: string, options?: RenderElementOptions): RenderedImage;
+		/**
+		 * Render every `<symbol>` and top-level group with an id
+		 * @param {RenderSymbolsOptions | undefined} options
+		 */
+		renderSymbols(options?: RenderSymbolsOptions): {
+			id: string;
+			image: RenderedImage | Uint8Array;
+		}[];
+		/**
+		 * Render a rectangle of user space at a given scale, without changing the viewbox
+		 * @param {{ x: number; y: number; width: number; height: number }} region
+		 * @param {RenderRegionOptions | undefined} options
+		 */
+		renderRegion(region: {
+			x: number;
+			y: number;
+			width: number;
+			height: number;
+		}, options?: RenderRegionOptions): RenderedImage;
+		/**
+		 * Render the image tile by tile, row by row, only the current tile is held in memory
+		 * @param {RenderTilesOptions | undefined} options
+		 * @param {(tile) => void} callback
+		 */
+		renderTiles(options: RenderTilesOptions | undefined, callback: (tile: {
+			x: number;
+			y: number;
+			width: number;
+			height: number;
+			image: RenderedImage | Uint8Array;
+		}) => void): void;
+		/**
+		 * Render every level of a Deep Zoom (DZI) pyramid from the vector tree, e.g. for OpenSeadragon
+		 * @param {TilePyramidOptions | undefined} options
+		 */
+		exportTilePyramid(options?: TilePyramidOptions): {
+			dzi?: string; // Only set with `format: "png"`.
+			width: number;
+			height: number;
+			tileSize: number;
+			overlap: number;
+			minLevel: number;
+			maxLevel: number;
+			tiles: {
+				level: number;
+				column: number;
+				row: number;
+				x: number;
+				y: number;
+				width: number;
+				height: number;
+				image: Uint8Array | RenderedImage;
+			}[];
+		};
+		/**
+		 * Encode the image as a PNG without holding the whole image in memory
+		 * @param {PngStreamOptions | undefined} options
+		 * @param {(chunk: Uint8Array) => void} callback
+		 */
+		renderPngStream(options: PngStreamOptions | undefined, callback: (chunk: Uint8Array) => void): void;
 		render(): RenderedImage;
 		toString(): string;
-		innerBBox(): BBox | undefined;
+		/**
+		 * Calculate a maximum bounding box of all visible elements in this SVG
+		 * @param {ElementBBoxOptions | undefined} options
+		 */
+		innerBBox(options?: ElementBBoxOptions): BBox | undefined;
 		getBBox(): BBox | undefined;
-		cropByBBox(bbox: BBox, padding?: number | undefined, square?: boolean | undefined): void;
+		/**
+		 * Calculate the bounding box of the element with `id` in user space
+		 * @param {string} id
+		 * @param {ElementBBoxOptions | undefined} options
+		 */
+		getElementBBox(id: string, options?: ElementBBoxOptions): BBox | undefined;
+		/**
+		 * Calculate the bounding box of every element with an id, in user space and in output pixels
+		 * @param {ElementBBoxOptions | undefined} options
+		 */
+		elementBBoxes(options?: ElementBBoxOptions): Record<string, {
+			user: BBox;
+			pixel: BBox;
+		}>;
+		/**
+		 * Get the ids of the elements painted at the pixel `(x, y)` of the rendered image,
+		 * from the outermost to the innermost.
+		 * @param {number} x
+		 * @param {number} y
+		 * @returns {string[]}
+		 */
+		elementsAtPoint(x: number, y: number): string[];
+		/**
+		 * Use a given `BBox` to crop the svg
+		 * @param {BBox} bbox
+		 * @param {number | CropByBBoxOptions | undefined} padding
+		 * @param {boolean | undefined} square
+		 * @param {boolean | undefined} relative
+		 */
+		cropByBBox(bbox: BBox, padding?: number | CropByBBoxOptions, square?: boolean, relative?: boolean): void;
+		cropByBBoxWithOptions(bbox: BBox, options?: string): void;
+		/**
+		 * Restore the viewbox and size of the SVG before `cropByBBox()`
+		 */
+		resetCrop(): void;
+		/**
+		 * Get the area cropped by `cropByBBox()` in user space, including the padding
+		 * @returns {BBox | undefined}
+		 */
+		getCrop(): BBox | undefined;
+		textContent(): any[];
+		warnings(): any[];
 		imagesToResolve(): any[];
 		resolveImage(href: string, buffer: Uint8Array): void;
 		readonly height: number;